@@ -2,7 +2,7 @@
 // To run this example, place a 16-bit, 16kHz, mono wav file with the spoken text "one two three four five six seven eight nine ten" named "audio.wav" in
 // the examples/data directory and run it with `cargo run --example alignment`.
 
-use pocketsphinx::{AlignmentIterItem, Config, LogMath};
+use pocketsphinx::{read_wav_i16, AlignmentIterItem, Config, LogMath};
 
 fn print_alignment_item(item: &AlignmentIterItem, logmath: &LogMath, indent: usize) {
     let name = item.get_name();
@@ -22,12 +22,7 @@ fn print_alignment_item(item: &AlignmentIterItem, logmath: &LogMath, indent: usi
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")?;
     let audio_path = format!("{}/examples/data/audio.wav", manifest_dir);
-    let audio = std::fs::read(audio_path)?;
-    // Skip the header and convert to i16
-    let audio_i16: Vec<i16> = audio[44..]
-        .chunks_exact(2)
-        .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]))
-        .collect();
+    let (audio_i16, _sample_rate) = read_wav_i16(&audio_path)?;
 
     // Create a config and set default acoustic model, dictionary, and language model
     let mut config = Config::default()?;
@@ -7,13 +7,7 @@ use std::sync::mpsc;
 
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use dasp::{interpolate::linear::Linear, signal, Signal};
-use pocketsphinx::{Config, Endpointer};
-
-#[derive(PartialEq)]
-enum SearchMode {
-    KeywordSpotter,
-    Grammar,
-}
+use pocketsphinx::{Config, Endpointer, SearchKind};
 
 pub fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create a channel to be able to send audio data to the decoder
@@ -79,7 +73,6 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
         .as_str(),
     )?;
     decoder.set_activate_search("keyword")?;
-    let mut search_mode = SearchMode::KeywordSpotter;
 
     // Endpointer requires a fixed frame size
     // In order to always process frame size parts of the audio stream
@@ -118,7 +111,7 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
                 // Check if the decoder has a hypothesis
                 match decoder.get_hyp()? {
                     Some((hyp, _score)) => {
-                        if search_mode == SearchMode::Grammar {
+                        if decoder.active_search_kind() == Some(SearchKind::Fsg) {
                             println!("Partial hypothesis: {}", hyp);
                         }
                     }
@@ -132,18 +125,16 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
                     // Check if the decoder has a hypothesis
                     match decoder.get_hyp()? {
                         Some((hyp, _score)) => {
-                            if search_mode == SearchMode::KeywordSpotter {
+                            if decoder.active_search_kind() == Some(SearchKind::Kws) {
                                 if hyp == "oh mighty computer" {
                                     println!("Keyphrase detected, switching to grammar search");
                                     // Switch to grammar search
                                     decoder.set_activate_search("commands")?;
-                                    search_mode = SearchMode::Grammar;
                                 }
                             } else {
                                 println!("Hypothesis: {}", hyp);
                                 // Switch back to keyword spotter search
                                 decoder.set_activate_search("keyword")?;
-                                search_mode = SearchMode::KeywordSpotter;
                             }
                         }
                         None => {}
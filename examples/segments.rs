@@ -2,17 +2,12 @@
 // To run this example, place a 16-bit, 16kHz, mono wav file named "audio.wav" in
 // the examples/data directory and run it with `cargo run --example segments`.
 
-use pocketsphinx::Config;
+use pocketsphinx::{read_wav_i16, Config};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")?;
     let audio_path = format!("{}/examples/data/audio.wav", manifest_dir);
-    let audio = std::fs::read(audio_path)?;
-    // Skip the header and convert to i16
-    let audio_i16: Vec<i16> = audio[44..]
-        .chunks_exact(2)
-        .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]))
-        .collect();
+    let (audio_i16, _sample_rate) = read_wav_i16(&audio_path)?;
 
     // Create a config and set default acoustic model, dictionary, and language model
     let mut config = Config::default()?;
@@ -36,7 +31,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // We can also get segments for nbest hypotheses
     // println!();
     // println!("N-Best (3): ");
-    // for nbest in decoder.get_nbest_iter().unwrap().take(3) {
+    // let mut nbest_iter = decoder.get_nbest_iter().unwrap();
+    // for _ in 0..3 {
+    //     let Some(nbest) = nbest_iter.next() else { break };
     //     for seg in nbest.get_seg() {
     //         let word = seg.get_word();
     //         let frames = seg.get_frames();
@@ -1,3 +1,4 @@
+use std::error::Error;
 use std::ffi::CStr;
 
 use crate::decoder::Decoder;
@@ -26,9 +27,8 @@ impl Alignment {
     ///
     /// # Returns
     /// A new alignment with the same underlying pointer.
-    pub fn retain(&mut self) -> Self {
+    pub fn retain(&self) -> Self {
         let retained_inner = unsafe { pocketsphinx_sys::ps_alignment_retain(self.inner) };
-        self.retained = true;
         Self {
             inner: retained_inner,
             retained: false,
@@ -47,6 +47,38 @@ impl Alignment {
         AlignmentIter::from_inner(inner)
     }
 
+    /// Get the names of all phones in the alignment in order.
+    pub fn phone_sequence(&self) -> Vec<String> {
+        self.get_phones()
+            .map(|phone| phone.get_name().to_string())
+            .collect()
+    }
+
+    /// Serialize the alignment as a JSON tree of words, phones and states.
+    ///
+    /// Each entry has the form `{"name": ..., "start": ..., "duration": ..., "score": ...}` with start and duration in seconds.
+    /// Words contain their phones in a `phones` array and phones contain their states in a `states` array.
+    ///
+    /// # Arguments
+    /// - `frame_rate` - Frames per second (`-frate`) used to convert frames to seconds, must be positive.
+    ///
+    /// # Returns
+    /// The JSON string or an error if the frame rate is not positive.
+    pub fn to_json(&self, frame_rate: i32) -> Result<String, Box<dyn Error>> {
+        if frame_rate <= 0 {
+            return Err("Frame rate must be positive".into());
+        }
+        let mut json = String::from("{\"words\":");
+        write_json_entries(
+            &mut json,
+            self.get_words(),
+            frame_rate as f64,
+            &["phones", "states"],
+        );
+        json.push('}');
+        Ok(json)
+    }
+
     /// Get state iterator over the alignment starting at the first state.
     pub fn get_states(&self) -> AlignmentIter {
         let inner = unsafe { pocketsphinx_sys::ps_alignment_states(self.inner) };
@@ -66,10 +98,57 @@ impl Drop for Alignment {
     }
 }
 
+/// Write alignment entries as a JSON array, nesting their children under the given keys.
+fn write_json_entries(
+    json: &mut String,
+    iter: AlignmentIter,
+    frame_rate: f64,
+    child_keys: &[&str],
+) {
+    json.push('[');
+    for (i, item) in iter.enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        let seg = item.get_seg();
+        json.push_str(&format!(
+            "{{\"name\":\"{}\",\"start\":{},\"duration\":{},\"score\":{}",
+            escape_json(item.get_name()),
+            seg.start as f64 / frame_rate,
+            seg.duration as f64 / frame_rate,
+            seg.score
+        ));
+        if let Some((key, grandchild_keys)) = child_keys.split_first() {
+            json.push_str(&format!(",\"{}\":", key));
+            match item.get_children() {
+                Some(children) => write_json_entries(json, children, frame_rate, grandchild_keys),
+                None => json.push_str("[]"),
+            }
+        }
+        json.push('}');
+    }
+    json.push(']');
+}
+
+/// Escape a string for use in a JSON string literal.
+fn escape_json(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 // We need a separate struct for the item that is not being dropped prematurely.
 
 pub struct AlignmentIterItem {
     inner: *mut pocketsphinx_sys::ps_alignment_iter_t,
+    parent: Option<String>,
 }
 
 impl AlignmentIterItem {
@@ -97,8 +176,18 @@ impl AlignmentIterItem {
         }
     }
 
+    /// Get the name of the parent segment, e.g. the word a phone belongs to.
+    ///
+    /// # Returns
+    /// The name of the parent segment or `None` if the item was not obtained via `AlignmentIterItem::get_children()`.
+    pub fn get_parent(&self) -> Option<&str> {
+        self.parent.as_deref()
+    }
+
     /// Get children iterator of the current alignment entry.
     ///
+    /// The children report the name of this entry as their parent, see `AlignmentIterItem::get_parent()`.
+    ///
     /// # Returns
     /// An iterator over the children of the current alignment entry or `None` if there are no children.
     pub fn get_children(&self) -> Option<AlignmentIter> {
@@ -106,7 +195,9 @@ impl AlignmentIterItem {
         if inner.is_null() {
             None
         } else {
-            Some(AlignmentIter::from_inner(inner))
+            let mut children = AlignmentIter::from_inner(inner);
+            children.parent = Some(self.get_name().to_string());
+            Some(children)
         }
     }
 }
@@ -115,6 +206,7 @@ pub struct AlignmentIter {
     inner: *mut pocketsphinx_sys::ps_alignment_iter_t,
     reached_end: bool,
     is_initial: bool,
+    parent: Option<String>,
 }
 
 impl AlignmentIter {
@@ -123,6 +215,7 @@ impl AlignmentIter {
             inner,
             reached_end: false,
             is_initial: true,
+            parent: None,
         }
     }
 }
@@ -146,7 +239,10 @@ impl Iterator for AlignmentIter {
             return None;
         }
 
-        let al = AlignmentIterItem { inner: self.inner };
+        let al = AlignmentIterItem {
+            inner: self.inner,
+            parent: self.parent.clone(),
+        };
         Some(al)
     }
 }
@@ -167,3 +263,72 @@ pub struct AlignmentSeg {
     /// Duration of this segment
     pub duration: i32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{decode_utterance, default_decoder, goforward_samples};
+
+    #[test]
+    fn escape_plain_text() {
+        assert_eq!(escape_json("hello"), "hello");
+        assert_eq!(escape_json(""), "");
+        assert_eq!(escape_json("<sil>"), "<sil>");
+    }
+
+    #[test]
+    fn escape_quotes_and_backslashes() {
+        assert_eq!(escape_json("say \"hi\""), r#"say \"hi\""#);
+        assert_eq!(escape_json("a\\b"), r"a\\b");
+        assert_eq!(escape_json("\\\""), r#"\\\""#);
+    }
+
+    #[test]
+    fn escape_control_characters() {
+        assert_eq!(escape_json("a\nb"), r"a\u000ab");
+        assert_eq!(escape_json("\t\r"), r"\u0009\u000d");
+        assert_eq!(escape_json("\u{0}\u{1f}"), r"\u0000\u001f");
+    }
+
+    #[test]
+    fn keep_non_ascii() {
+        assert_eq!(escape_json("héllo wörld"), "héllo wörld");
+    }
+
+    #[test]
+    fn to_json_rejects_non_positive_frame_rate() {
+        let mut decoder = default_decoder(&[]);
+        let samples = goforward_samples();
+        decode_utterance(&mut decoder, &samples).unwrap();
+        decoder.set_alignment(None).unwrap();
+        decode_utterance(&mut decoder, &samples).unwrap();
+        let alignment = decoder.get_alignment().unwrap();
+
+        assert!(alignment.to_json(0).is_err());
+        assert!(alignment.to_json(-100).is_err());
+        let json = alignment.to_json(100).unwrap();
+        assert!(json.starts_with("{\"words\":[{\"name\":"));
+        assert!(!json.contains("inf") && !json.contains("NaN"));
+    }
+
+    #[test]
+    fn children_report_parent_name() {
+        let mut decoder = default_decoder(&[]);
+        let samples = goforward_samples();
+        decode_utterance(&mut decoder, &samples).unwrap();
+        decoder.set_alignment(None).unwrap();
+        decode_utterance(&mut decoder, &samples).unwrap();
+        let alignment = decoder.get_alignment().unwrap();
+
+        let mut n_children = 0;
+        for word in alignment.get_words() {
+            assert_eq!(word.get_parent(), None);
+            let name = word.get_name().to_string();
+            for phone in word.get_children().into_iter().flatten() {
+                assert_eq!(phone.get_parent(), Some(name.as_str()));
+                n_children += 1;
+            }
+        }
+        assert!(n_children > 0);
+    }
+}
@@ -0,0 +1,467 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::io::Write;
+
+/// Read a 16-bit mono PCM WAV file.
+///
+/// Unlike skipping a fixed 44 byte header, this walks the RIFF chunks, so files with additional chunks (e.g. `LIST`) before the audio data are read correctly.
+///
+/// # Arguments
+/// - `path` - Path to the WAV file.
+///
+/// # Returns
+/// A tuple of the samples and the sample rate of the file or an error if the file is not a 16-bit mono PCM WAV file.
+pub fn read_wav_i16(path: &str) -> Result<(Vec<i16>, u32), Box<dyn Error>> {
+    let bytes = std::fs::read(path)?;
+    parse_wav_i16(&bytes)
+}
+
+/// Write samples to a 16-bit mono PCM WAV file.
+///
+/// # Arguments
+/// - `path`        - Path of the file to create. An existing file is overwritten.
+/// - `samples`     - Audio samples to write.
+/// - `sample_rate` - Sample rate of the audio.
+pub fn write_wav(path: &str, samples: &[i16], sample_rate: u32) -> Result<(), Box<dyn Error>> {
+    let data_size = u32::try_from(samples.len() * 2)
+        .ok()
+        .filter(|size| *size <= u32::MAX - 36)
+        .ok_or("Too many samples for a WAV file")?;
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    // PCM, mono
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    // Byte rate and block align
+    writer.write_all(&(sample_rate * 2).to_le_bytes())?;
+    writer.write_all(&2u16.to_le_bytes())?;
+    writer.write_all(&16u16.to_le_bytes())?;
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+    for sample in samples {
+        writer.write_all(&sample.to_le_bytes())?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub(crate) fn parse_wav_i16(bytes: &[u8]) -> Result<(Vec<i16>, u32), Box<dyn Error>> {
+    if bytes.len() < 12 {
+        return Err(format!("Truncated WAV header ({} bytes)", bytes.len()).into());
+    }
+    if &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("Not a WAV file (missing RIFF/WAVE header)".into());
+    }
+
+    let mut sample_rate = None;
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let chunk_id = &bytes[offset..offset + 4];
+        let chunk_size = u32::from_le_bytes([
+            bytes[offset + 4],
+            bytes[offset + 5],
+            bytes[offset + 6],
+            bytes[offset + 7],
+        ]) as usize;
+        let body_start = offset + 8;
+        // Files written by streaming tools often carry a bogus size for the last chunk, so clamp it to what is available
+        let body_end = body_start.saturating_add(chunk_size).min(bytes.len());
+        let body = &bytes[body_start..body_end];
+
+        match chunk_id {
+            b"fmt " => {
+                if body.len() < 16 {
+                    return Err("Truncated WAV fmt chunk".into());
+                }
+                let audio_format = u16::from_le_bytes([body[0], body[1]]);
+                let channels = u16::from_le_bytes([body[2], body[3]]);
+                let bits_per_sample = u16::from_le_bytes([body[14], body[15]]);
+                if audio_format != 1 || channels != 1 || bits_per_sample != 16 {
+                    return Err(format!(
+                        "Unsupported WAV format (format {}, {} channels, {} bits), only 16-bit mono PCM is supported",
+                        audio_format, channels, bits_per_sample
+                    )
+                    .into());
+                }
+                sample_rate = Some(u32::from_le_bytes([body[4], body[5], body[6], body[7]]));
+            }
+            b"data" => {
+                let sample_rate = sample_rate.ok_or("WAV data chunk found before fmt chunk")?;
+                let samples = body
+                    .chunks_exact(2)
+                    .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]))
+                    .collect();
+                return Ok((samples, sample_rate));
+            }
+            _ => {}
+        }
+
+        // Chunks are padded to an even number of bytes
+        offset = body_start.saturating_add(chunk_size + (chunk_size & 1));
+    }
+
+    Err("WAV file contains no data chunk".into())
+}
+
+/// Parse a 16-bit mono PCM NIST Sphere file.
+///
+/// The header starts with `NIST_1A`, followed by the header size and `name -type value` fields up to `end_head`.
+pub(crate) fn parse_nist_i16(bytes: &[u8]) -> Result<(Vec<i16>, u32), Box<dyn Error>> {
+    if bytes.len() < 16 || &bytes[0..7] != b"NIST_1A" {
+        return Err("Not a NIST Sphere file (missing NIST_1A header)".into());
+    }
+    let header_size: usize = std::str::from_utf8(&bytes[8..16])?
+        .trim()
+        .parse()
+        .map_err(|_| "Invalid NIST header size")?;
+    if header_size > bytes.len() {
+        return Err("Truncated NIST header".into());
+    }
+
+    let header = String::from_utf8_lossy(&bytes[16..header_size]);
+    let mut sample_rate = None;
+    let mut big_endian = false;
+    for line in header.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            ["end_head", ..] => break,
+            ["sample_rate", _, value] => sample_rate = Some(value.parse()?),
+            ["channel_count", _, value] if *value != "1" => {
+                return Err("Unsupported NIST channel count, only mono is supported".into());
+            }
+            ["sample_n_bytes", _, value] if *value != "2" => {
+                return Err("Unsupported NIST sample size, only 16-bit is supported".into());
+            }
+            ["sample_coding", _, value] if !value.starts_with("pcm") => {
+                return Err("Unsupported NIST sample coding, only PCM is supported".into());
+            }
+            ["sample_byte_format", _, value] => big_endian = *value == "10",
+            _ => {}
+        }
+    }
+
+    let sample_rate = sample_rate.ok_or("NIST header has no sample_rate")?;
+    let from_bytes = if big_endian {
+        i16::from_be_bytes
+    } else {
+        i16::from_le_bytes
+    };
+    let samples = bytes[header_size..]
+        .chunks_exact(2)
+        .map(|chunk| from_bytes([chunk[0], chunk[1]]))
+        .collect();
+    Ok((samples, sample_rate))
+}
+
+/// RMS level below which audio is considered silent (about -60 dBFS).
+const SILENCE_RMS: f64 = 32.0;
+
+/// Level statistics of an audio buffer, see `analyze()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioStats {
+    /// Largest absolute sample value.
+    pub peak: u16,
+    /// Root mean square of the samples.
+    pub rms: f64,
+    /// Fraction of samples at the minimum or maximum sample value.
+    pub clipped_fraction: f64,
+    /// Whether the RMS level is below -60 dBFS.
+    pub silent: bool,
+}
+
+/// Analyze the level of audio before decoding.
+///
+/// Input that is too quiet or clipped is a common cause of poor recognition, so this can be used to warn users about it.
+///
+/// # Arguments
+/// - `samples` - Audio samples to analyze.
+///
+/// # Returns
+/// The level statistics. An empty buffer is reported as silent.
+pub fn analyze(samples: &[i16]) -> AudioStats {
+    if samples.is_empty() {
+        return AudioStats {
+            peak: 0,
+            rms: 0.0,
+            clipped_fraction: 0.0,
+            silent: true,
+        };
+    }
+
+    let mut peak = 0;
+    let mut sum_squares = 0.0;
+    let mut clipped = 0;
+    for &sample in samples {
+        peak = peak.max(sample.unsigned_abs());
+        sum_squares += sample as f64 * sample as f64;
+        if sample == i16::MAX || sample == i16::MIN {
+            clipped += 1;
+        }
+    }
+    let rms = (sum_squares / samples.len() as f64).sqrt();
+    AudioStats {
+        peak,
+        rms,
+        clipped_fraction: clipped as f64 / samples.len() as f64,
+        silent: rms < SILENCE_RMS,
+    }
+}
+
+/// Fixed size buffer holding the most recent samples of an audio stream.
+///
+/// When more samples are pushed than fit, the oldest ones are discarded.
+/// This is useful for always-on listeners that need look-back (pre-roll) audio, e.g. when a keyphrase is detected.
+pub struct RingBuffer {
+    samples: VecDeque<i16>,
+    capacity: usize,
+}
+
+impl RingBuffer {
+    /// Create a ring buffer holding up to `capacity` samples.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Create a ring buffer that holds `seconds` of audio at the given sample rate.
+    pub fn with_duration(sample_rate: u32, seconds: f32) -> Self {
+        Self::new((sample_rate as f32 * seconds).round() as usize)
+    }
+
+    /// Append samples, discarding the oldest samples if the buffer would overflow.
+    pub fn push(&mut self, samples: &[i16]) {
+        let samples = &samples[samples.len().saturating_sub(self.capacity)..];
+        let overflow = (self.samples.len() + samples.len()).saturating_sub(self.capacity);
+        self.samples.drain(..overflow);
+        self.samples.extend(samples);
+    }
+
+    /// Remove and return the oldest `frame_size` samples.
+    ///
+    /// # Returns
+    /// The frame or `None` if fewer than `frame_size` samples are buffered.
+    pub fn read_frame(&mut self, frame_size: usize) -> Option<Vec<i16>> {
+        if self.samples.len() < frame_size {
+            return None;
+        }
+        Some(self.samples.drain(..frame_size).collect())
+    }
+
+    /// Copy the most recent samples without removing them.
+    ///
+    /// # Arguments
+    /// - `count` - Maximum number of samples to return.
+    ///
+    /// # Returns
+    /// Up to `count` of the most recently pushed samples in chronological order.
+    pub fn latest(&self, count: usize) -> Vec<i16> {
+        let skip = self.samples.len().saturating_sub(count);
+        self.samples.iter().skip(skip).copied().collect()
+    }
+
+    /// Number of samples currently buffered.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Whether no samples are buffered.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Maximum number of samples the buffer holds.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Discard all buffered samples.
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a WAV file from a 16-bit mono PCM `fmt ` chunk and the given extra chunks.
+    fn wav_bytes(sample_rate: u32, chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut fmt = Vec::new();
+        fmt.extend_from_slice(&1u16.to_le_bytes());
+        fmt.extend_from_slice(&1u16.to_le_bytes());
+        fmt.extend_from_slice(&sample_rate.to_le_bytes());
+        fmt.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+        fmt.extend_from_slice(&2u16.to_le_bytes());
+        fmt.extend_from_slice(&16u16.to_le_bytes());
+
+        let mut body = b"WAVE".to_vec();
+        for (id, data) in std::iter::once((b"fmt ", fmt.as_slice())).chain(chunks.iter().copied()) {
+            body.extend_from_slice(id);
+            body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            body.extend_from_slice(data);
+            if data.len() % 2 == 1 {
+                body.push(0);
+            }
+        }
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&body);
+        bytes
+    }
+
+    #[test]
+    fn parse_wav_truncated_header() {
+        let err = parse_wav_i16(b"RIFF\x24\x00").unwrap_err();
+        assert_eq!(err.to_string(), "Truncated WAV header (6 bytes)");
+        assert!(parse_wav_i16(&[]).is_err());
+    }
+
+    #[test]
+    fn parse_wav_truncated_fmt_chunk() {
+        let bytes = wav_bytes(16000, &[]);
+        assert!(parse_wav_i16(&bytes[..24]).is_err());
+    }
+
+    #[test]
+    fn parse_wav_missing_data_chunk() {
+        let bytes = wav_bytes(16000, &[(b"LIST", b"INFO")]);
+        let err = parse_wav_i16(&bytes).unwrap_err();
+        assert_eq!(err.to_string(), "WAV file contains no data chunk");
+    }
+
+    #[test]
+    fn parse_wav_odd_data_size() {
+        let bytes = wav_bytes(8000, &[(b"data", &[1, 0, 2, 0, 3])]);
+        let (samples, sample_rate) = parse_wav_i16(&bytes).unwrap();
+        assert_eq!(samples, [1, 2]);
+        assert_eq!(sample_rate, 8000);
+    }
+
+    #[test]
+    fn parse_wav_skips_chunks_before_data() {
+        let bytes = wav_bytes(16000, &[(b"LIST", b"odd"), (b"data", &[0xff, 0x7f])]);
+        let (samples, _) = parse_wav_i16(&bytes).unwrap();
+        assert_eq!(samples, [i16::MAX]);
+    }
+
+    #[test]
+    fn write_wav_read_wav_round_trip() {
+        let path = std::env::temp_dir().join(format!("pocketsphinx-rs-{}.wav", std::process::id()));
+        let path = path.to_str().unwrap();
+        let samples: Vec<i16> = vec![0, 1, -1, i16::MAX, i16::MIN, 1234, -4321];
+        write_wav(path, &samples, 22050).unwrap();
+        let result = read_wav_i16(path);
+        std::fs::remove_file(path).unwrap();
+
+        let (read_samples, sample_rate) = result.unwrap();
+        assert_eq!(read_samples, samples);
+        assert_eq!(sample_rate, 22050);
+    }
+
+    #[test]
+    fn ring_buffer_wraparound() {
+        let mut buffer = RingBuffer::new(4);
+        buffer.push(&[1, 2, 3]);
+        assert_eq!(buffer.read_frame(2), Some(vec![1, 2]));
+        // Wraps past the end of the initial allocation
+        buffer.push(&[4, 5, 6]);
+        assert_eq!(buffer.len(), 4);
+        assert_eq!(buffer.latest(4), [3, 4, 5, 6]);
+        assert_eq!(buffer.read_frame(3), Some(vec![3, 4, 5]));
+        buffer.push(&[7]);
+        assert_eq!(buffer.latest(10), [6, 7]);
+    }
+
+    #[test]
+    fn ring_buffer_overwrites_oldest_when_full() {
+        let mut buffer = RingBuffer::new(3);
+        buffer.push(&[1, 2, 3]);
+        buffer.push(&[4]);
+        assert_eq!(buffer.latest(3), [2, 3, 4]);
+        // A push larger than the capacity keeps only its newest samples
+        buffer.push(&[5, 6, 7, 8, 9]);
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.latest(3), [7, 8, 9]);
+        assert_eq!(buffer.latest(2), [8, 9]);
+    }
+
+    #[test]
+    fn ring_buffer_draining() {
+        let mut buffer = RingBuffer::new(8);
+        buffer.push(&[1, 2, 3, 4, 5]);
+        assert_eq!(buffer.read_frame(2), Some(vec![1, 2]));
+        assert_eq!(buffer.read_frame(2), Some(vec![3, 4]));
+        assert_eq!(buffer.read_frame(2), None);
+        assert_eq!(buffer.len(), 1);
+        buffer.clear();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.read_frame(1), None);
+        assert_eq!(buffer.capacity(), 8);
+    }
+
+    #[test]
+    fn ring_buffer_with_duration() {
+        assert_eq!(RingBuffer::with_duration(16000, 0.5).capacity(), 8000);
+    }
+
+    #[test]
+    fn analyze_empty() {
+        let stats = analyze(&[]);
+        assert_eq!(
+            stats,
+            AudioStats {
+                peak: 0,
+                rms: 0.0,
+                clipped_fraction: 0.0,
+                silent: true,
+            }
+        );
+    }
+
+    #[test]
+    fn analyze_silence() {
+        let stats = analyze(&[0; 1600]);
+        assert_eq!(stats.peak, 0);
+        assert_eq!(stats.rms, 0.0);
+        assert_eq!(stats.clipped_fraction, 0.0);
+        assert!(stats.silent);
+
+        // Low level noise is still silent
+        let noise: Vec<i16> = (0..1600)
+            .map(|i| if i % 2 == 0 { 20 } else { -20 })
+            .collect();
+        let stats = analyze(&noise);
+        assert_eq!(stats.peak, 20);
+        assert_eq!(stats.rms, 20.0);
+        assert!(stats.silent);
+    }
+
+    #[test]
+    fn analyze_full_scale_square_wave() {
+        let square: Vec<i16> = (0..1600)
+            .map(|i| if (i / 8) % 2 == 0 { i16::MAX } else { i16::MIN })
+            .collect();
+        let stats = analyze(&square);
+        assert_eq!(stats.peak, 32768);
+        assert!((stats.rms - 32767.5).abs() < 0.01);
+        assert_eq!(stats.clipped_fraction, 1.0);
+        assert!(!stats.silent);
+    }
+
+    #[test]
+    fn analyze_partially_clipped() {
+        let samples = [0, 1000, i16::MAX, -1000, i16::MIN, 500, 0, 0];
+        let stats = analyze(&samples);
+        assert_eq!(stats.peak, 32768);
+        assert_eq!(stats.clipped_fraction, 0.25);
+        assert!(!stats.silent);
+    }
+}
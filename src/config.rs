@@ -1,6 +1,7 @@
 use std::error::Error;
 
 use crate::decoder::Decoder;
+use crate::error::to_cstring;
 
 pub struct Config {
     inner: *mut pocketsphinx_sys::ps_config_t,
@@ -38,6 +39,7 @@ impl Config {
     ///
     /// # Returns
     /// Newly created configuration or an Error on failure (should not happen, but check it anyway).
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Result<Self, Box<dyn Error>> {
         let mut config = Self::new()?;
         config.set_default_search_args();
@@ -54,9 +56,8 @@ impl Config {
     }
 
     /// Returns a retained config and assures the underlying config is not freed before the retained config is dropped.
-    pub fn retain(&mut self) -> Self {
+    pub fn retain(&self) -> Self {
         let retained_inner = unsafe { pocketsphinx_sys::ps_config_retain(self.inner) };
-        self.retained = true;
         Config {
             inner: retained_inner,
             retained: false,
@@ -86,7 +87,7 @@ impl Config {
     /// # Returns
     /// Newly created configuration or an Error on failure (such as invalid or missing parameters).
     pub fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
-        let c_json = std::ffi::CString::new(json)?;
+        let c_json = to_cstring("json", json)?;
         let config = unsafe {
             pocketsphinx_sys::ps_config_parse_json(std::ptr::null_mut(), c_json.as_ptr())
         };
@@ -119,7 +120,7 @@ impl Config {
     /// # Returns
     /// Ok or an Error on failure (such as invalid or missing parameters).
     pub fn extend_from_json(&mut self, json: &str) -> Result<(), Box<dyn Error>> {
-        let c_json = std::ffi::CString::new(json)?;
+        let c_json = to_cstring("json", json)?;
         let result = unsafe { pocketsphinx_sys::ps_config_parse_json(self.inner, c_json.as_ptr()) };
 
         if result.is_null() {
@@ -151,7 +152,7 @@ impl Config {
     /// # Returns
     /// A tuple of the parameter type and a boolean indicating whether the parameter is required.
     pub fn get_typeof_param(&self, name: &str) -> Result<(ParamType, bool), Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
+        let c_name = to_cstring("name", name)?;
         let param_type = unsafe { pocketsphinx_sys::ps_config_typeof(self.inner, c_name.as_ptr()) };
         if param_type == 0 {
             return Err("Unknown parameter".into());
@@ -174,6 +175,29 @@ impl Config {
         Ok((param_type, required))
     }
 
+    /// Get the names of all parameters of a given type.
+    ///
+    /// # Arguments
+    /// - `param_type` - Type of the parameters to return.
+    ///
+    /// # Returns
+    /// The parameter names in the order of the pocketsphinx parameter definitions.
+    pub fn params_of_type(&self, param_type: ParamType) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut arg = unsafe { pocketsphinx_sys::ps_args() };
+        while !arg.is_null() && unsafe { !(*arg).name.is_null() } {
+            let name = unsafe { std::ffi::CStr::from_ptr((*arg).name) }.to_string_lossy();
+            let name = name.trim_start_matches('-');
+            if let Ok((t, _required)) = self.get_typeof_param(name) {
+                if t == param_type {
+                    names.push(name.to_string());
+                }
+            }
+            arg = unsafe { arg.add(1) };
+        }
+        names
+    }
+
     /// Validate configuration.
     ///
     /// Currently this just checks that you haven't specified multiple types of grammars or language models at the same time.
@@ -182,11 +206,37 @@ impl Config {
         validation_result == 0
     }
 
+    /// Get the kind of search the configuration sets up, before the decoder is initialized.
+    ///
+    /// This complements `is_valid()` by telling which of the `jsgf`, `fsg`, `lm`, `keyphrase` and `allphone` parameters is set.
+    ///
+    /// # Returns
+    /// The grammar mode, `GrammarMode::None` if none of the parameters is set or an error if more than one is set.
+    pub fn grammar_mode(&self) -> Result<GrammarMode, Box<dyn Error>> {
+        let modes = [
+            ("jsgf", GrammarMode::Jsgf),
+            ("fsg", GrammarMode::Fsg),
+            ("lm", GrammarMode::Lm),
+            ("keyphrase", GrammarMode::Keyphrase),
+            ("allphone", GrammarMode::Allphone),
+        ];
+        let mut set_modes = modes
+            .into_iter()
+            .filter(|(name, _)| self.get_str(name).is_ok_and(|value| !value.is_empty()));
+        match (set_modes.next(), set_modes.next()) {
+            (None, _) => Ok(GrammarMode::None),
+            (Some((_, mode)), None) => Ok(mode),
+            (Some((first, _)), Some((second, _))) => {
+                Err(format!("Multiple grammar modes are set ({} and {})", first, second).into())
+            }
+        }
+    }
+
     /// Get a boolean-valued parameter.
     ///
     /// If the parameter does not have an integer or boolean type, this will print an error and return an Err.
     pub fn get_bool(&self, name: &str) -> Result<bool, Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
+        let c_name = to_cstring("name", name)?;
         let value = unsafe { pocketsphinx_sys::ps_config_bool(self.inner, c_name.as_ptr()) };
 
         if value == -1 {
@@ -200,7 +250,7 @@ impl Config {
     ///
     /// If the parameter does not have an integer or boolean type, this will convert `value` appropriately.
     pub fn set_bool(&mut self, name: &str, value: bool) -> Result<(), Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
+        let c_name = to_cstring("name", name)?;
         let value = if value { 1 } else { 0 };
 
         let _result =
@@ -213,7 +263,7 @@ impl Config {
     ///
     /// If the parameter does not have an integer or boolean type, this will print an error and return 0. So don't do that.
     pub fn get_int(&self, name: &str) -> Result<i64, Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
+        let c_name = to_cstring("name", name)?;
         let value = unsafe { pocketsphinx_sys::ps_config_int(self.inner, c_name.as_ptr()) };
 
         Ok(value)
@@ -223,7 +273,7 @@ impl Config {
     ///
     /// If the parameter does not have an integer or boolean type, this will convert `value` appropriately.
     pub fn set_int(&mut self, name: &str, value: i64) -> Result<(), Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
+        let c_name = to_cstring("name", name)?;
 
         let _result =
             unsafe { pocketsphinx_sys::ps_config_set_int(self.inner, c_name.as_ptr(), value) };
@@ -235,7 +285,7 @@ impl Config {
     ///
     /// If the parameter does not have a floating-point type, this will print an error and return 0.
     pub fn get_float(&self, name: &str) -> Result<f64, Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
+        let c_name = to_cstring("name", name)?;
         let value = unsafe { pocketsphinx_sys::ps_config_float(self.inner, c_name.as_ptr()) };
 
         Ok(value)
@@ -245,7 +295,7 @@ impl Config {
     ///
     /// If the parameter does not have a floating-point type, this will convert `value` appropriately.
     pub fn set_float(&mut self, name: &str, value: f64) -> Result<(), Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
+        let c_name = to_cstring("name", name)?;
 
         let _result =
             unsafe { pocketsphinx_sys::ps_config_set_float(self.inner, c_name.as_ptr(), value) };
@@ -258,7 +308,7 @@ impl Config {
     /// If the parameter does not have a string type, this will print an error and return an Err.
     /// Notably, it will NOT format an `integer` or `float` for you, because that would involve allocating memory. So don't do that.
     pub fn get_str(&self, name: &str) -> Result<String, Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
+        let c_name = to_cstring("name", name)?;
         let value = unsafe { pocketsphinx_sys::ps_config_str(self.inner, c_name.as_ptr()) };
 
         if value.is_null() {
@@ -276,8 +326,8 @@ impl Config {
     /// If the parameter does not have a string type, this will convert `value` appropriately.
     /// For boolean parameters, any string matching /^[yt1]/ will be `true`, while any string matching /^[nf0]/ will be `false`.
     pub fn set_str(&mut self, name: &str, value: &str) -> Result<(), Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
-        let c_value = std::ffi::CString::new(value)?;
+        let c_name = to_cstring("name", name)?;
+        let c_value = to_cstring("value", value)?;
 
         let _result = unsafe {
             pocketsphinx_sys::ps_config_set_str(self.inner, c_name.as_ptr(), c_value.as_ptr())
@@ -286,6 +336,38 @@ impl Config {
         Ok(())
     }
 
+    /// Set the speaker adaptation (MLLR) transform to load when the decoder is initialized.
+    ///
+    /// This sets the `mllr` parameter, so the transform is applied by `Decoder::new()` or `Decoder::reinit()` without a separate update call.
+    ///
+    /// # Arguments
+    /// - `path` - Path to the MLLR transformation file.
+    pub fn set_mllr(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
+        if !std::path::Path::new(path).is_file() {
+            return Err(format!("MLLR file {} does not exist", path).into());
+        }
+        self.set_str("mllr", path)
+    }
+
+    /// Enable or disable the tree-structured lexicon search pass (`fwdtree`) of N-Gram searches.
+    pub fn set_fwdtree(&mut self, enabled: bool) -> Result<(), Box<dyn Error>> {
+        self.set_bool("fwdtree", enabled)
+    }
+
+    /// Enable or disable the flat lexicon search pass (`fwdflat`) of N-Gram searches.
+    ///
+    /// When `fwdtree` is enabled as well, this pass rescores the words found by the tree search.
+    pub fn set_fwdflat(&mut self, enabled: bool) -> Result<(), Box<dyn Error>> {
+        self.set_bool("fwdflat", enabled)
+    }
+
+    /// Enable or disable the bestpath search pass (`bestpath`) over the word lattice.
+    ///
+    /// This is required for meaningful posterior probabilities from `Decoder::get_posterior()`.
+    pub fn set_bestpath(&mut self, enabled: bool) -> Result<(), Box<dyn Error>> {
+        self.set_bool("bestpath", enabled)
+    }
+
     /// Set configuration parameters (actually just sample rate) from a sound file.
     ///
     /// If the file is unreadable, unsupported or incompatible with the existing feature extraction parameters, this will print an error message and fail.
@@ -302,13 +384,13 @@ impl Config {
         soundfile: &str,
         name: Option<&str>,
     ) -> Result<(), Box<dyn Error>> {
-        let c_soundfile = std::ffi::CString::new(soundfile)?;
-        let c_name_ptr = match name {
-            Some(name) => std::ffi::CString::new(name)?.as_ptr(),
-            None => std::ptr::null_mut(),
-        };
+        let c_soundfile = to_cstring("soundfile", soundfile)?;
+        let c_name = name.map(|name| to_cstring("name", name)).transpose()?;
+        let c_name_ptr = c_name
+            .as_ref()
+            .map_or(std::ptr::null(), |c_name| c_name.as_ptr());
         // Create C File pointer
-        let c_file = unsafe { libc::fopen(c_soundfile.as_ptr(), "rb".as_ptr() as *const i8) };
+        let c_file = unsafe { libc::fopen(c_soundfile.as_ptr(), c"rb".as_ptr()) };
         if c_file.is_null() {
             return Err("Failed to open soundfile".into());
         }
@@ -333,13 +415,13 @@ impl Config {
         wavfile: &str,
         name: Option<&str>,
     ) -> Result<(), Box<dyn Error>> {
-        let c_soundfile = std::ffi::CString::new(wavfile)?;
-        let c_name_ptr = match name {
-            Some(name) => std::ffi::CString::new(name)?.as_ptr(),
-            None => std::ptr::null_mut(),
-        };
+        let c_soundfile = to_cstring("wavfile", wavfile)?;
+        let c_name = name.map(|name| to_cstring("name", name)).transpose()?;
+        let c_name_ptr = c_name
+            .as_ref()
+            .map_or(std::ptr::null(), |c_name| c_name.as_ptr());
         // Create C File pointer
-        let c_file = unsafe { libc::fopen(c_soundfile.as_ptr(), "rb".as_ptr() as *const i8) };
+        let c_file = unsafe { libc::fopen(c_soundfile.as_ptr(), c"rb".as_ptr()) };
         if c_file.is_null() {
             return Err("Failed to open wavfile".into());
         }
@@ -364,13 +446,13 @@ impl Config {
         nistfile: &str,
         name: Option<&str>,
     ) -> Result<(), Box<dyn Error>> {
-        let c_soundfile = std::ffi::CString::new(nistfile)?;
-        let c_name_ptr = match name {
-            Some(name) => std::ffi::CString::new(name)?.as_ptr(),
-            None => std::ptr::null_mut(),
-        };
+        let c_soundfile = to_cstring("nistfile", nistfile)?;
+        let c_name = name.map(|name| to_cstring("name", name)).transpose()?;
+        let c_name_ptr = c_name
+            .as_ref()
+            .map_or(std::ptr::null(), |c_name| c_name.as_ptr());
         // Create C File pointer
-        let c_file = unsafe { libc::fopen(c_soundfile.as_ptr(), "rb".as_ptr() as *const i8) };
+        let c_file = unsafe { libc::fopen(c_soundfile.as_ptr(), c"rb".as_ptr()) };
         if c_file.is_null() {
             return Err("Failed to open nistfile".into());
         }
@@ -427,9 +509,126 @@ impl Drop for Config {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ParamType {
     Integer,
     Boolean,
     Float,
     String,
 }
+
+/// Kind of search set up by a configuration, see `Config::grammar_mode()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrammarMode {
+    /// No grammar or language model is set.
+    None,
+    /// JSGF grammar (`jsgf`).
+    Jsgf,
+    /// Finite state grammar (`fsg`).
+    Fsg,
+    /// N-Gram language model (`lm`).
+    Lm,
+    /// Keyphrase spotting (`keyphrase`).
+    Keyphrase,
+    /// Phone loop (`allphone`).
+    Allphone,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{decode_utterance, goforward_samples, temp_path, GOFORWARD_TEXT};
+
+    /// Write an identity MLLR transform for the 39-dimensional features of the default model.
+    fn write_identity_mllr(path: &str) {
+        let n = 39;
+        let mut content = format!("1\n1\n{}\n", n);
+        for i in 0..n {
+            let row: Vec<&str> = (0..n).map(|j| if i == j { "1.0" } else { "0.0" }).collect();
+            content.push_str(&row.join(" "));
+            content.push('\n');
+        }
+        content.push_str(&vec!["0.0"; n].join(" "));
+        content.push('\n');
+        content.push_str(&vec!["1.0"; n].join(" "));
+        content.push('\n');
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn set_mllr_is_applied_on_init() {
+        let path = temp_path("identity.mllr");
+        write_identity_mllr(&path);
+
+        let mut config = Config::default().unwrap();
+        config.set_mllr(&path).unwrap();
+        assert_eq!(config.get_str("mllr").unwrap(), path);
+        let mut decoder = config.init_decoder().unwrap();
+        let (hyp, _) = decode_utterance(&mut decoder, &goforward_samples()).unwrap();
+        assert_eq!(hyp, GOFORWARD_TEXT);
+
+        // A truncated transform makes initialization fail, so it is really read
+        std::fs::write(&path, "1\n1\n39\n1.0\n").unwrap();
+        let mut config = Config::default().unwrap();
+        config.set_mllr(&path).unwrap();
+        assert!(config.init_decoder().is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn set_mllr_rejects_missing_file() {
+        let mut config = Config::default().unwrap();
+        assert!(config.set_mllr(&temp_path("missing.mllr")).is_err());
+        assert!(config.get_str("mllr").is_err());
+    }
+
+    #[test]
+    fn search_pass_helpers_configure_decoder() {
+        let mut config = Config::default().unwrap();
+        config.set_fwdtree(true).unwrap();
+        config.set_fwdflat(false).unwrap();
+        config.set_bestpath(false).unwrap();
+        assert!(config.get_bool("fwdtree").unwrap());
+        assert!(!config.get_bool("fwdflat").unwrap());
+        assert!(!config.get_bool("bestpath").unwrap());
+
+        let mut decoder = config.init_decoder().unwrap();
+        assert!(!decoder.bestpath_enabled());
+        let (hyp, _) = decode_utterance(&mut decoder, &goforward_samples()).unwrap();
+        assert_eq!(hyp, GOFORWARD_TEXT);
+        assert_eq!(decoder.get_posterior(), None);
+    }
+
+    #[test]
+    fn params_of_type_filters_by_type() {
+        let config = Config::default().unwrap();
+        let booleans = config.params_of_type(ParamType::Boolean);
+        let integers = config.params_of_type(ParamType::Integer);
+        let floats = config.params_of_type(ParamType::Float);
+        let strings = config.params_of_type(ParamType::String);
+
+        assert!(booleans.iter().any(|name| name == "bestpath"));
+        assert!(integers.iter().any(|name| name == "frate"));
+        assert!(floats.iter().any(|name| name == "samprate"));
+        assert!(strings.iter().any(|name| name == "hmm"));
+        assert!(!booleans.iter().any(|name| name.starts_with('-')));
+        for name in &booleans {
+            assert_eq!(config.get_typeof_param(name).unwrap().0, ParamType::Boolean);
+            assert!(!strings.contains(name));
+        }
+    }
+
+    #[test]
+    fn from_wavfile_with_name_sets_sample_rate() {
+        let path = temp_path("config-8k.wav");
+        crate::audio::write_wav(&path, &[0; 800], 8000).unwrap();
+        let mut config = Config::default().unwrap();
+        config.from_wavfile(&path, Some("config-8k")).unwrap();
+        assert_eq!(config.get_float("samprate").unwrap(), 8000.0);
+
+        let mut config = Config::default().unwrap();
+        config.from_soundfile(&path, Some("config-8k")).unwrap();
+        assert_eq!(config.get_float("samprate").unwrap(), 8000.0);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
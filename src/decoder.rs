@@ -1,17 +1,117 @@
+use std::cell::RefCell;
 use std::error::Error;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
 
 use crate::alignment_iter::Alignment;
-use crate::config::Config;
+use crate::audio;
+use crate::config::{Config, ParamType};
+use crate::endpointer::Endpointer;
+use crate::error::to_cstring;
 use crate::fsg::FSG;
+use crate::lattice::Lattice;
 use crate::logmath::LogMath;
-use crate::nbest_iter::NBestIter;
-use crate::search_iter::SearchIter;
-use crate::seg_iter::SegIter;
+use crate::mllr::MLLR;
+use crate::nbest_iter::{NBestHypothesis, NBestIter, NBestResult};
+use crate::pcm_sink::PcmSink;
+use crate::search_iter::{ActiveSearch, SearchIter, SearchKind};
+use crate::seg_iter::{strip_alternate_pronunciation, BoundarySymbols, SegIter, WordSegment};
+use crate::streaming::{RecognitionEvent, StreamingHandle};
+use crate::subtitle;
+use crate::tokenizer::{Tokenizer, WhitespaceTokenizer};
+use crate::vad::{VADClass, VAD};
 use crate::Ngram;
 
+/// Configuration parameters of the feature extraction, which can be applied with `Decoder::reinit_feat()` instead of a full reinitialization.
+const FEATURE_PARAMS: &[&str] = &[
+    "alpha",
+    "ceplen",
+    "cmn",
+    "cmninit",
+    "dither",
+    "doublebw",
+    "feat",
+    "frate",
+    "input_endian",
+    "lda",
+    "ldadim",
+    "lifter",
+    "logspec",
+    "lowerf",
+    "ncep",
+    "nfft",
+    "nfilt",
+    "remove_dc",
+    "remove_noise",
+    "samprate",
+    "seed",
+    "smoothspec",
+    "svspec",
+    "transform",
+    "unit_area",
+    "upperf",
+    "varnorm",
+    "warp_params",
+    "warp_type",
+    "wlen",
+];
+
+/// Number of samples read and processed at once by `Decoder::decode_raw_reader()` and `Decoder::decode_raw_file_cancellable()`.
+const RAW_CHUNK_SAMPLES: usize = 4096;
+
+/// Insertion probability used for silence and fillers when they are disabled.
+const DISABLED_FILLER_PROB: f64 = 1e-100;
+
+/// Counter for unique names of the temporary files written by `Decoder::load_senscr()`.
+static SENSCR_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A finalized utterance as (text, start, end) with start and end time in seconds.
+pub type ContinuousResult = (String, f64, f64);
+
+/// A hypothesis as (text, path score, is final).
+pub type HypothesisStatus = (String, i32, bool);
+
+/// Utterance state of a pocketsphinx decoder, shared by all of its handles (see `Decoder::retain()`).
+#[derive(Debug, Default)]
+struct UtteranceState {
+    /// Whether an utterance has been started since the handle was created.
+    started: AtomicBool,
+    /// Whether an utterance is active, i.e. it has been started and not ended yet.
+    active: AtomicBool,
+    /// Whether the last utterance was ended with `Decoder::abort_utterance()`.
+    aborted: AtomicBool,
+}
+
+/// Speech recognizer wrapping a pocketsphinx decoder.
+///
+/// # Changing search parameters
+///
+/// pocketsphinx only reads most search parameters when the decoder is initialized.
+/// Setters such as `Decoder::set_lw()`, `Decoder::set_wip()`, `Decoder::set_beam()` and `Decoder::set_fillers_enabled()` therefore reinitialize the decoder from its configuration, like `Decoder::reinit()`.
+/// This discards everything that was added at runtime: searches (`Decoder::add_lm()`, `Decoder::add_jsgf_string()`, ...) and dictionary words (`Decoder::add_word()`).
+/// Change the parameters first and add searches and words afterwards, or set the parameters in the `Config` before creating the decoder.
+/// Feature extraction parameters (e.g. `Decoder::set_frame_rate()`) only reinitialize the feature extraction and keep them.
 pub struct Decoder {
     inner: *mut pocketsphinx_sys::ps_decoder_t,
     retained: bool,
+    utt_state: Arc<UtteranceState>,
+    /// Number of samples passed to `Decoder::process_raw()` in the current utterance.
+    utt_samples: usize,
+    /// Number of samples passed to `Decoder::process_raw()` since the decoder was created.
+    total_samples: usize,
+    /// Identifier of the current utterance set with `Decoder::set_utt_id()`.
+    utt_id: Option<String>,
+    /// Values of `fillprob`, `silprob` and `fsgusefiller` before `Decoder::set_fillers_enabled()` disabled the fillers.
+    saved_filler_params: Option<(f64, f64, bool)>,
+    /// Names of the phone loop searches added with `Decoder::add_allphone()` or `Decoder::add_allphone_file()`, which the C API can not tell apart from other searches.
+    allphone_searches: Vec<String>,
+    /// Samples passed to `Decoder::feed_continuous()` that do not fill a complete endpointer frame yet.
+    pending_samples: Vec<i16>,
+    /// Symbols read from the filler dictionary by `Decoder::boundary_symbols()`, cleared when the decoder is reinitialized.
+    boundary_symbols: RefCell<Option<BoundarySymbols>>,
 }
 
 impl Decoder {
@@ -20,13 +120,15 @@ impl Decoder {
     /// # Arguments
     /// - `config` - Configuration to use for decoder initialization. If `None`, the decoder will be allocated but not initialized. You can proceed to initialize it with `Decoder::reinit()`.
     pub fn new(config: Option<&mut Config>) -> Result<Self, Box<dyn Error>> {
+        // The decoder takes its own reference to the configuration, so the caller's handle still frees its reference on drop
         let config_ptr = match config {
-            Some(config) => {
-                config.set_retained(true);
-                config.get_inner()
-            }
+            Some(config) => config.get_inner(),
             None => std::ptr::null_mut(),
         };
+        Self::init(config_ptr)
+    }
+
+    fn init(config_ptr: *mut pocketsphinx_sys::ps_config_t) -> Result<Self, Box<dyn Error>> {
         let decoder = unsafe { pocketsphinx_sys::ps_init(config_ptr) };
 
         if decoder.is_null() {
@@ -35,13 +137,21 @@ impl Decoder {
             Ok(Decoder {
                 inner: decoder,
                 retained: false,
+                utt_state: Arc::default(),
+                utt_samples: 0,
+                total_samples: 0,
+                utt_id: None,
+                saved_filler_params: None,
+                allphone_searches: Vec::new(),
+                pending_samples: Vec::new(),
+                boundary_symbols: RefCell::default(),
             })
         }
     }
 
     /// Actives search with the provided name.
     pub fn set_activate_search(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
+        let c_name = to_cstring("name", name)?;
 
         let result = unsafe { pocketsphinx_sys::ps_activate_search(self.inner, c_name.as_ptr()) };
 
@@ -67,11 +177,59 @@ impl Decoder {
         }
     }
 
+    /// Returns the kind of the current search in decoder.
+    ///
+    /// # Returns
+    /// The kind of the active search or `None` if no search is active.
+    pub fn active_search_kind(&self) -> Option<SearchKind> {
+        let name = self.get_current_search().ok()?;
+        // The alignment search is a grammar search internally
+        if name == "_align" {
+            return Some(SearchKind::Align);
+        }
+        // The accessors only return the object of the named search if it has the matching type, the decoder keeps ownership of it
+        let c_name = to_cstring("name", &name).ok()?;
+        let kind = if unsafe {
+            !pocketsphinx_sys::ps_get_kws(self.inner, c_name.as_ptr()).is_null()
+        } {
+            SearchKind::Kws
+        } else if unsafe { !pocketsphinx_sys::ps_get_fsg(self.inner, c_name.as_ptr()).is_null() } {
+            SearchKind::Fsg
+        } else if unsafe { !pocketsphinx_sys::ps_get_lm(self.inner, c_name.as_ptr()).is_null() } {
+            SearchKind::Ngram
+        } else if self.allphone_searches.contains(&name)
+            || (name == "_default" && self.get_config().get_str("allphone").is_ok())
+        {
+            SearchKind::Allphone
+        } else {
+            SearchKind::Unknown
+        };
+        Some(kind)
+    }
+
+    /// Returns the object backing the current search in decoder.
+    ///
+    /// # Returns
+    /// The language model, grammar or keyphrase of the active search, depending on its kind.
+    pub fn active_search_object(&self) -> ActiveSearch {
+        let kind = self.active_search_kind();
+        let search = match kind {
+            Some(SearchKind::Ngram) => self.get_lm(None).map(ActiveSearch::Ngram),
+            // Hold an own reference, so the grammar stays valid if the search is removed
+            Some(SearchKind::Fsg) => self
+                .get_fsg(None)
+                .map(|fsg| ActiveSearch::Fsg(fsg.retain())),
+            Some(SearchKind::Kws) => self.get_kws(None).ok().flatten().map(ActiveSearch::Kws),
+            _ => None,
+        };
+        search.unwrap_or(ActiveSearch::Other(kind))
+    }
+
     /// Removes a search module and releases its resources.
     ///
     /// Removes a search module previously added with using `add_jsgf()`, `add_fsg()`, `add_lm()`, `add_kws()`, etc.
     pub fn remove_search(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
+        let c_name = to_cstring("name", name)?;
 
         let result = unsafe { pocketsphinx_sys::ps_remove_search(self.inner, c_name.as_ptr()) };
 
@@ -87,53 +245,83 @@ impl Decoder {
         SearchIter::from_decoder(self)
     }
 
+    /// Capture which searches exist and which one is active.
+    ///
+    /// Only the names are stored, so this is cheap and can be used to push and pop recognition contexts with `Decoder::restore_state()`.
+    pub fn save_state(&self) -> DecoderState {
+        DecoderState {
+            searches: self.get_search_iter().collect(),
+            active_search: self.get_current_search().ok(),
+        }
+    }
+
+    /// Restore the searches captured with `Decoder::save_state()`.
+    ///
+    /// Searches added after the state was saved are removed and the previously active search is activated again.
+    /// Searches removed in the meantime can not be recreated from their names, so this returns an error without changing anything if one is missing.
+    pub fn restore_state(&mut self, state: &DecoderState) -> Result<(), Box<dyn Error>> {
+        let current: Vec<String> = self.get_search_iter().collect();
+        if let Some(missing) = state.searches.iter().find(|name| !current.contains(name)) {
+            return Err(format!(
+                "Search {} no longer exists and can not be restored",
+                missing
+            )
+            .into());
+        }
+
+        if let Some(active_search) = &state.active_search {
+            self.set_activate_search(active_search)?;
+        }
+        for name in current.iter().filter(|name| !state.searches.contains(name)) {
+            self.remove_search(name)?;
+        }
+        Ok(())
+    }
+
     /// Get the language model or lmset object associated with a search.
     ///
     /// # Arguments
     /// - `name` - Name of language model search, or `None` for current search.
     ///
     /// # Returns
-    /// The language model (possibly set of language models) object for this decoder.
-    /// The decoder retains ownership of this pointer, so you should not attempt to free it manually.
-    /// Use `Ngram::retain()` if you wish to reuse it elsewhere.
+    /// The language model (possibly set of language models) object for this decoder or `None` if name does not correspond to an N-Gram search.
+    /// The returned model holds its own reference, so it stays valid for inspecting counts, vocabulary and scores even if the search is removed or the decoder is dropped.
     pub fn get_lm(&self, name: Option<&str>) -> Option<Ngram> {
-        Ngram::from_decoder(self, name)
+        Ngram::from_decoder(self, name).map(|lm| lm.retain())
     }
 
     /// Adds new search based on N-gram language model.
     ///
     /// Associates N-gram search with the provided name. The search can be activated using `Decoder::activate_search()`.
-    pub fn add_lm(&mut self, name: &str, lm: &mut Ngram) -> Result<(), Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
+    /// This also works for models built in memory, e.g. with `Ngram::set_init()`, without writing them to a file first.
+    /// The decoder takes its own reference to the model, so `lm` can be dropped afterwards.
+    ///
+    /// # Arguments
+    /// - `name` - Name of the new search.
+    /// - `lm`   - The language model (or set of language models) to search with.
+    pub fn add_lm(&mut self, name: &str, lm: &Ngram) -> Result<(), Box<dyn Error>> {
+        let c_name = to_cstring("name", name)?;
 
         let result =
             unsafe { pocketsphinx_sys::ps_add_lm(self.inner, c_name.as_ptr(), lm.get_inner()) };
 
-        // TODO: Check if this is correct (undocumented...)
-        if result == -1 {
-            Err("Failed to add LM".into())
-        } else {
-            Ok(())
-        }
+        check_search_result(result, || format!("Failed to add LM search {}", name))
     }
 
     /// Adds new search based on N-gram language model.
     ///
     /// Convenient method to load N-gram model and create a search.
     pub fn add_lm_file(&mut self, name: &str, path: &str) -> Result<(), Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
-        let c_path = std::ffi::CString::new(path)?;
+        let c_name = to_cstring("name", name)?;
+        let c_path = to_cstring("path", path)?;
 
         let result = unsafe {
             pocketsphinx_sys::ps_add_lm_file(self.inner, c_name.as_ptr(), c_path.as_ptr())
         };
 
-        // TODO: Check if this is correct (undocumented...)
-        if result == -1 {
-            Err("Failed to add LM file".into())
-        } else {
-            Ok(())
-        }
+        check_search_result(result, || {
+            format!("Failed to add LM search {} from {}", name, path)
+        })
     }
 
     /// Get the finite-state grammar set object associated with a search.
@@ -147,21 +335,82 @@ impl Decoder {
         FSG::from_decoder(self, name)
     }
 
+    /// Get the sentence markers, silence and filler words of the loaded model.
+    ///
+    /// They are read from the filler dictionary (`fdict`) of the configuration, which is used to tell fillers from recognized words in results.
+    /// The file is read once and cached until the decoder is reinitialized or a new filler dictionary is loaded.
+    ///
+    /// # Returns
+    /// The symbols of the model or the pocketsphinx defaults (`<s>`, `</s>`, `<sil>`) if the filler dictionary cannot be read.
+    pub fn boundary_symbols(&self) -> BoundarySymbols {
+        self.boundary_symbols
+            .borrow_mut()
+            .get_or_insert_with(|| {
+                self.get_config()
+                    .get_str("fdict")
+                    .ok()
+                    .and_then(|fdict| std::fs::read_to_string(fdict).ok())
+                    .map(|content| BoundarySymbols::from_filler_dict(&content))
+                    .unwrap_or_default()
+            })
+            .clone()
+    }
+
+    /// Get the words a search can recognize.
+    ///
+    /// For FSG searches these are the words of the grammar, for N-Gram searches the unigrams of the language model.
+    /// Fillers and sentence markers are not included.
+    ///
+    /// # Arguments
+    /// - `name` - Name of the search or `None` for the current search.
+    ///
+    /// # Returns
+    /// The vocabulary of the search. Empty for other kinds of searches (e.g. keyphrase spotting).
+    pub fn search_vocabulary(&self, name: Option<&str>) -> Result<Vec<String>, Box<dyn Error>> {
+        let c_name = name.map(|name| to_cstring("name", name)).transpose()?;
+        let c_name_ptr = c_name
+            .as_ref()
+            .map_or(std::ptr::null(), |c_name| c_name.as_ptr());
+
+        let mut words = Vec::new();
+        let fsg = unsafe { pocketsphinx_sys::ps_get_fsg(self.inner, c_name_ptr) };
+        if !fsg.is_null() {
+            let (n_word, vocab) = unsafe { ((*fsg).n_word, (*fsg).vocab) };
+            for i in 0..n_word.max(0) as usize {
+                let c_word = unsafe { *vocab.add(i) };
+                if !c_word.is_null() {
+                    words.push(
+                        unsafe { std::ffi::CStr::from_ptr(c_word) }
+                            .to_string_lossy()
+                            .into_owned(),
+                    );
+                }
+            }
+        } else if let Some(lm) = self.get_lm(name) {
+            let n_unigrams = lm.get_counts().first().copied().unwrap_or(0);
+            for wid in 0..n_unigrams as i32 {
+                words.push(lm.word(wid));
+            }
+        }
+
+        let symbols = self.boundary_symbols();
+        words.retain(|word| !symbols.is_filler(word));
+        words.sort();
+        words.dedup();
+        Ok(words)
+    }
+
     /// Adds new search based on finite state grammar.
     ///
     /// Associates FSG search with the provided name.
     /// The search can be activated using `Decoder::set_active_search()`.
     pub fn add_fsg(&mut self, name: &str, fsg: &mut FSG) -> Result<(), Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
+        let c_name = to_cstring("name", name)?;
 
         let result =
             unsafe { pocketsphinx_sys::ps_add_fsg(self.inner, c_name.as_ptr(), fsg.get_inner()) };
 
-        if result == -1 {
-            Err("Failed to add FSG".into())
-        } else {
-            Ok(())
-        }
+        check_search_result(result, || format!("Failed to add FSG search {}", name))
     }
 
     /// Adds new search using JSGF model.
@@ -170,18 +419,16 @@ impl Decoder {
     /// - name - Name of the search.
     /// - path - Path to JSGF model.
     pub fn add_jsgf_file(&mut self, name: &str, path: &str) -> Result<(), Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
-        let c_path = std::ffi::CString::new(path)?;
+        let c_name = to_cstring("name", name)?;
+        let c_path = to_cstring("path", path)?;
 
         let result = unsafe {
             pocketsphinx_sys::ps_add_jsgf_file(self.inner, c_name.as_ptr(), c_path.as_ptr())
         };
 
-        if result == -1 {
-            Err("Failed to add JSGF file".into())
-        } else {
-            Ok(())
-        }
+        check_search_result(result, || {
+            format!("Failed to add JSGF search {} from {}", name, path)
+        })
     }
 
     /// Adds new search using JSGF model.
@@ -192,18 +439,14 @@ impl Decoder {
     /// - name - Name of the search.
     /// - jsgf - JSGF model.
     pub fn add_jsgf_string(&mut self, name: &str, jsgf: &str) -> Result<(), Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
-        let c_jsgf = std::ffi::CString::new(jsgf)?;
+        let c_name = to_cstring("name", name)?;
+        let c_jsgf = to_cstring("jsgf", jsgf)?;
 
         let result = unsafe {
             pocketsphinx_sys::ps_add_jsgf_string(self.inner, c_name.as_ptr(), c_jsgf.as_ptr())
         };
 
-        if result == -1 {
-            Err("Failed to add JSGF string".into())
-        } else {
-            Ok(())
-        }
+        check_search_result(result, || format!("Failed to add JSGF search {}", name))
     }
 
     /// Get the keyphrase associated with a KWS search
@@ -214,10 +457,11 @@ impl Decoder {
     /// # Returns
     /// The current keyphrase to spot, or `None` if name does not correspond to a KWS search
     pub fn get_kws(&self, name: Option<&str>) -> Result<Option<String>, Box<dyn Error>> {
-        let c_name_ptr = match name {
-            Some(name) => std::ffi::CString::new(name)?.as_ptr(),
-            None => std::ptr::null(),
-        };
+        // Keep the CString alive until after the FFI call
+        let c_name = name.map(|name| to_cstring("name", name)).transpose()?;
+        let c_name_ptr = c_name
+            .as_ref()
+            .map_or(std::ptr::null(), |c_name| c_name.as_ptr());
 
         let keyphrase = unsafe { pocketsphinx_sys::ps_get_kws(self.inner, c_name_ptr) };
 
@@ -236,75 +480,71 @@ impl Decoder {
     ///
     /// Associates KWS search with the provided name. The search can be activated using `Decoder::activate_search()`.
     pub fn add_kws_file(&mut self, name: &str, keyfile: &str) -> Result<(), Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
-        let c_keyfile = std::ffi::CString::new(keyfile)?;
+        let c_name = to_cstring("name", name)?;
+        let c_keyfile = to_cstring("keyfile", keyfile)?;
 
         let result = unsafe {
             pocketsphinx_sys::ps_add_kws(self.inner, c_name.as_ptr(), c_keyfile.as_ptr())
         };
 
-        // TODO: Check if this is correct (undocumented...)
-        if result == -1 {
-            Err("Failed to add KWS".into())
-        } else {
-            Ok(())
-        }
+        check_search_result(result, || {
+            format!("Failed to add KWS search {} from {}", name, keyfile)
+        })
     }
 
     /// Adds new keyphrase to spot
     ///
     /// Associates KWS search with the provided name. The search can be activated using `Decoder::activate_search()`.
     pub fn add_keyphrase(&mut self, name: &str, keyphrase: &str) -> Result<(), Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
-        let c_keyphrase = std::ffi::CString::new(keyphrase)?;
+        let c_name = to_cstring("name", name)?;
+        let c_keyphrase = to_cstring("keyphrase", keyphrase)?;
 
         let result = unsafe {
             pocketsphinx_sys::ps_add_keyphrase(self.inner, c_name.as_ptr(), c_keyphrase.as_ptr())
         };
 
-        // TODO: Check if this is correct (undocumented...)
-        if result == -1 {
-            Err("Failed to add keyphrase".into())
-        } else {
-            Ok(())
-        }
+        check_search_result(result, || {
+            format!("Failed to add keyphrase search {}", name)
+        })
     }
 
     /// Adds new search based on phone N-gram language model.
     ///
     /// Associates N-gram search with the provided name. The search can be activated using `Decoder::activate_search()`.
+    /// Unlike `Decoder::add_allphone_file()`, this accepts a model built or adapted at runtime.
+    /// The recognized phones are available as segments with `Decoder::get_seg_iter()`.
+    ///
+    /// # Arguments
+    /// - `name` - Name of the new search.
+    /// - `lm`   - Phone N-Gram model, whose words are the phones of the acoustic model.
     pub fn add_allphone(&mut self, name: &str, lm: &Ngram) -> Result<(), Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
+        let c_name = to_cstring("name", name)?;
 
         let result = unsafe {
             pocketsphinx_sys::ps_add_allphone(self.inner, c_name.as_ptr(), lm.get_inner())
         };
 
-        // TODO: Check if this is correct (undocumented...)
-        if result == -1 {
-            Err("Failed to add allphone".into())
-        } else {
-            Ok(())
-        }
+        check_search_result(result, || format!("Failed to add allphone search {}", name))?;
+        self.allphone_searches.push(name.to_string());
+        Ok(())
     }
 
     /// Adds new search based on phone N-gram language model.
     ///
     /// Convenient method to load N-gram model and create a search.
     pub fn add_allphone_file(&mut self, name: &str, path: &str) -> Result<(), Box<dyn Error>> {
-        let c_name = std::ffi::CString::new(name)?;
-        let c_path = std::ffi::CString::new(path)?;
+        let c_name = to_cstring("name", name)?;
+        let c_path = to_cstring("path", path)?;
 
         let result = unsafe {
             pocketsphinx_sys::ps_add_allphone_file(self.inner, c_name.as_ptr(), c_path.as_ptr())
         };
 
-        // TODO: Check if this is correct (undocumented...)
-        if result == -1 {
-            Err("Failed to add allphone file".into())
-        } else {
-            Ok(())
-        }
+        check_search_result(result, || {
+            format!("Failed to add allphone search {} from {}", name, path)
+        })?;
+        self.allphone_searches.push(name.to_string());
+        Ok(())
     }
 
     /// Set up decoder to force-align a word sequence.
@@ -316,16 +556,173 @@ impl Decoder {
     /// Word alignments are available with `Decoder::seg_iter()`. To obtain phoneme or state segmentations, you must subsequently call `Decoder::set_alignment()` and re-run decoding.
     /// It's tough son, but it's life.
     pub fn set_align_text(&mut self, words: &str) -> Result<(), Box<dyn Error>> {
-        let c_words = std::ffi::CString::new(words)?;
+        let c_words = to_cstring("words", words)?;
 
         let result = unsafe { pocketsphinx_sys::ps_set_align_text(self.inner, c_words.as_ptr()) };
 
-        // TODO: Check if this is correct (undocumented...)
-        if result == -1 {
-            Err("Failed to set align text".into())
-        } else {
-            Ok(())
+        check_search_result(result, || {
+            format!(
+                "Failed to set align text \"{}\", are all words in the dictionary?",
+                words
+            )
+        })
+    }
+
+    /// Force align text to audio and return word timings only.
+    ///
+    /// This runs a single decoding pass with `Decoder::set_align_text()`, skipping the phone and state-level pass of `Decoder::set_alignment()`, which makes it much faster if only word timestamps are needed.
+    /// The previously active search is activated again afterwards.
+    ///
+    /// # Arguments
+    /// - `text`    - Words to align, separated by whitespace. All words must be in the dictionary.
+    /// - `samples` - The complete audio of the utterance.
+    ///
+    /// # Returns
+    /// The aligned words in order. Fillers such as silence are not included.
+    pub fn align_words(
+        &mut self,
+        text: &str,
+        samples: &[i16],
+    ) -> Result<Vec<WordSegment>, Box<dyn Error>> {
+        self.align_words_with(text, samples, &WhitespaceTokenizer)
+    }
+
+    /// Force align text to audio and return word timings, splitting the text into words with a custom tokenizer.
+    ///
+    /// See `Decoder::align_words()`.
+    ///
+    /// # Arguments
+    /// - `text`      - Text to align. All words produced by the tokenizer must be in the dictionary.
+    /// - `samples`   - The complete audio of the utterance.
+    /// - `tokenizer` - Tokenizer splitting the text into dictionary words.
+    pub fn align_words_with(
+        &mut self,
+        text: &str,
+        samples: &[i16],
+        tokenizer: &dyn Tokenizer,
+    ) -> Result<Vec<WordSegment>, Box<dyn Error>> {
+        let previous_search = self.get_current_search()?;
+        let words = self.align_words_pass(&tokenizer.tokenize(text).join(" "), samples);
+        self.set_activate_search(&previous_search)?;
+        words
+    }
+
+    /// Run the alignment pass of `Decoder::align_words_with()`, leaving the alignment search active.
+    fn align_words_pass(
+        &mut self,
+        words: &str,
+        samples: &[i16],
+    ) -> Result<Vec<WordSegment>, Box<dyn Error>> {
+        self.set_align_text(words)?;
+        self.start_utt()?;
+        self.process_raw(samples, false, true)?;
+        self.end_utt()?;
+
+        let symbols = self.boundary_symbols();
+        let seg_iter = self.get_seg_iter().ok_or("Failed to align text")?;
+        let words = seg_iter
+            .map(|seg| {
+                let frames = seg.get_frames();
+                WordSegment {
+                    word: seg.get_word(),
+                    start: frames.start,
+                    end: frames.end,
+                }
+            })
+            .filter(|segment| !symbols.is_filler(&segment.word))
+            .collect();
+        Ok(words)
+    }
+
+    /// Decode a complete utterance and collect the best hypothesis, its word timings and the N-best alternatives.
+    ///
+    /// All results are taken from a single decoding pass, which avoids decoding the audio again for each of them.
+    ///
+    /// # Arguments
+    /// - `samples` - The complete audio of the utterance.
+    /// - `n`       - Maximum number of N-best hypotheses to collect.
+    pub fn decode_full(&mut self, samples: &[i16], n: usize) -> Result<DecodeFull, Box<dyn Error>> {
+        self.start_utt()?;
+        self.process_raw(samples, false, true)?;
+        self.end_utt()?;
+
+        let best = self.get_hyp()?;
+        let segments = match self.get_seg_iter() {
+            Some(seg_iter) => seg_iter
+                .map(|seg| {
+                    let frames = seg.get_frames();
+                    WordSegment {
+                        word: seg.get_word(),
+                        start: frames.start,
+                        end: frames.end,
+                    }
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        let nbest = match self.get_nbest_iter() {
+            Some(nbest_iter) => nbest_iter.take_hypotheses(n),
+            None => Vec::new(),
+        };
+        Ok(DecodeFull {
+            best,
+            segments,
+            nbest,
+        })
+    }
+
+    /// Force align the top N-best hypotheses of the last utterance.
+    ///
+    /// Each distinct hypothesis is aligned to the audio with a word pass and a phone and state-level pass, which makes it possible to compare the timing of alternatives.
+    /// The previously active search is activated again afterwards.
+    ///
+    /// # Arguments
+    /// - `n`       - Maximum number of hypotheses to align.
+    /// - `samples` - The complete audio of the last utterance.
+    ///
+    /// # Returns
+    /// A vector of the hypotheses and their alignments in N-best order.
+    pub fn align_nbest(
+        &mut self,
+        n: usize,
+        samples: &[i16],
+    ) -> Result<Vec<(String, Alignment)>, Box<dyn Error>> {
+        let mut hypotheses: Vec<String> = Vec::with_capacity(n);
+        if let Some(mut nbest_iter) = self.get_nbest_iter() {
+            while let Some(nbest) = nbest_iter.next() {
+                if hypotheses.len() == n {
+                    break;
+                }
+                let hyp = nbest.get_hyp().hypothesis;
+                if !hyp.trim().is_empty() && !hypotheses.contains(&hyp) {
+                    hypotheses.push(hyp);
+                }
+            }
         }
+
+        let previous_search = self.get_current_search()?;
+        let mut alignments = Vec::with_capacity(hypotheses.len());
+        for hyp in hypotheses {
+            self.set_align_text(&hyp)?;
+            self.start_utt()?;
+            self.process_raw(samples, false, true)?;
+            self.end_utt()?;
+
+            self.set_alignment(None)?;
+            self.start_utt()?;
+            self.process_raw(samples, false, true)?;
+            self.end_utt()?;
+
+            // The decoder replaces its alignment on the next pass, so keep a reference of our own
+            let alignment = self
+                .get_alignment()
+                .ok_or("Failed to get alignment")?
+                .retain();
+            alignments.push((hyp, alignment));
+        }
+        self.set_activate_search(&previous_search)?;
+
+        Ok(alignments)
     }
 
     /// Set up decoder to run phone and state-level alignment.
@@ -335,6 +732,9 @@ impl Decoder {
     /// To align, run or re-run decoding as usual, then call `Decoder::get_alignment()` to get the resulting alignment.
     /// Note that if you call this function before rerunning decoding, you can obtain the phone and state sequence, but the durations will be invalid (phones and states will inherit the parent word's duration).
     ///
+    /// Re-running decoding on raw audio extracts the features again, since pocketsphinx does not expose the features it computed in the first pass.
+    /// To avoid the repeated feature extraction, compute cepstra once outside the decoder and run both passes with `Decoder::process_cep()`.
+    ///
     /// # Arguments
     /// - `alignment` - Usually `None`, which means to construct an alignment from the current search hypothesis (this does not work with allphone or keyword spotting). You can also pass `Some(&Alignment)` here if you have one. The search will retain but not copy it, so after running decoding it will be updated with new durations. You can set starts and durations for words or phones (not states) to constrain the alignment.
     ///
@@ -358,8 +758,11 @@ impl Decoder {
     /// As noted above, if decoding has not been run, this will contain invalid durations, but that may still be useful if you just want to know the state sequence.
     ///
     /// # Returns
-    /// Current alignment or `None`. This pointer is owned by the decoder, so you must call `Alignment::retain()` on it if you wish to keep it outside the lifetime of the decoder.
+    /// Current alignment or `None` if no alignment was computed (e.g. `Decoder::set_alignment()` was not called), which is not an error. This pointer is owned by the decoder, so you must call `Alignment::retain()` on it if you wish to keep it outside the lifetime of the decoder.
     pub fn get_alignment(&self) -> Option<Alignment> {
+        if self.is_utt_aborted() {
+            return None;
+        }
         Alignment::from_decoder(self)
     }
 
@@ -373,6 +776,7 @@ impl Decoder {
     /// The decoder retains ownership of the pointer config, so you should free it when no longer used.
     pub fn reinit(&mut self, config: &Config) -> Result<(), Box<dyn Error>> {
         let result = unsafe { pocketsphinx_sys::ps_reinit(self.inner, config.get_inner()) };
+        self.boundary_symbols.take();
 
         if result == -1 {
             Err("Failed to reinitialize decoder".into())
@@ -397,9 +801,305 @@ impl Decoder {
         }
     }
 
-    // ps_get_cmn
+    /// Apply a feature parameter file (`feat.params`) and reinitialize the feature extraction.
+    ///
+    /// The file contains one `-name value` pair per line, like the `feat.params` of an acoustic model.
+    /// Parameters unknown to pocketsphinx are ignored. Unlike `Decoder::reinit()`, searches and words added at runtime are kept.
+    ///
+    /// # Arguments
+    /// - `path` - Path to the feature parameter file.
+    pub fn load_feat_params(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        let mut config = self.get_config();
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, value) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| format!("Missing value in feature parameter line: {}", line))?;
+            let name = name.trim_start_matches('-');
+            if config.get_typeof_param(name).is_ok() {
+                config.set_str(name, value.trim())?;
+            }
+        }
+        self.reinit_feat(&config)
+    }
+
+    /// Set the language model weight.
+    ///
+    /// The weight is applied to the `lw` configuration parameter and the decoder is reinitialized in order to pick it up.
+    /// **This discards all searches and dictionary words added at runtime**, see [Changing search parameters](Decoder#changing-search-parameters).
+    ///
+    /// # Arguments
+    /// - `lw` - New language model weight, must be positive.
+    pub fn set_lw(&mut self, lw: f32) -> Result<(), Box<dyn Error>> {
+        if !(lw.is_finite() && lw > 0.0) {
+            return Err("Language model weight must be a positive number".into());
+        }
+        self.update_search_params(|config| config.set_float("lw", lw as f64))
+    }
+
+    /// Get the language model weight.
+    ///
+    /// # Returns
+    /// The current value of the `lw` configuration parameter.
+    pub fn get_lw(&self) -> Result<f32, Box<dyn Error>> {
+        Ok(self.get_config().get_float("lw")? as f32)
+    }
+
+    /// Set the word insertion penalty.
+    ///
+    /// The penalty is applied to the `wip` configuration parameter and the decoder is reinitialized in order to pick it up.
+    /// **This discards all searches and dictionary words added at runtime**, see [Changing search parameters](Decoder#changing-search-parameters).
+    ///
+    /// # Arguments
+    /// - `wip` - New word insertion penalty, must be positive.
+    pub fn set_wip(&mut self, wip: f64) -> Result<(), Box<dyn Error>> {
+        if !(wip.is_finite() && wip > 0.0) {
+            return Err("Word insertion penalty must be a positive number".into());
+        }
+        self.update_search_params(|config| config.set_float("wip", wip))
+    }
+
+    /// Get the word insertion penalty.
+    ///
+    /// # Returns
+    /// The current value of the `wip` configuration parameter.
+    pub fn get_wip(&self) -> Result<f64, Box<dyn Error>> {
+        self.get_config().get_float("wip")
+    }
+
+    /// Get the pruning beam widths.
+    ///
+    /// # Returns
+    /// The current values of the `beam`, `pbeam`, `wbeam` and `lpbeam` configuration parameters.
+    pub fn get_beam(&self) -> Result<BeamSettings, Box<dyn Error>> {
+        let config = self.get_config();
+        Ok(BeamSettings {
+            beam: config.get_float("beam")?,
+            pbeam: config.get_float("pbeam")?,
+            wbeam: config.get_float("wbeam")?,
+            lpbeam: config.get_float("lpbeam")?,
+        })
+    }
+
+    /// Set the pruning beam widths.
+    ///
+    /// The widths are applied to the configuration and the decoder is reinitialized in order to pick them up.
+    /// **This discards all searches and dictionary words added at runtime**, see [Changing search parameters](Decoder#changing-search-parameters).
+    ///
+    /// # Arguments
+    /// - `beams` - New beam widths, all must be positive. Smaller values prune less and make decoding slower but more accurate.
+    pub fn set_beam(&mut self, beams: &BeamSettings) -> Result<(), Box<dyn Error>> {
+        let params = [
+            ("beam", beams.beam),
+            ("pbeam", beams.pbeam),
+            ("wbeam", beams.wbeam),
+            ("lpbeam", beams.lpbeam),
+        ];
+        if let Some((name, _)) = params
+            .iter()
+            .find(|(_, value)| !(value.is_finite() && *value > 0.0))
+        {
+            return Err(format!("Beam width {} must be a positive number", name).into());
+        }
+        self.update_search_params(|config| {
+            for (name, value) in params {
+                config.set_float(name, value)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Enable or disable the recognition of silence and filler words (e.g. `<sil>`, `[noise]`).
+    ///
+    /// Disabling sets the `fillprob` and `silprob` configuration parameters to a negligible probability and turns off `fsgusefiller`, which is useful for clean grammar-only recognition.
+    /// Enabling restores the values these parameters had before they were disabled, it does nothing if the fillers were not disabled with this method.
+    /// **Both reinitialize the decoder, which discards all searches and dictionary words added at runtime**, see [Changing search parameters](Decoder#changing-search-parameters).
+    ///
+    /// # Arguments
+    /// - `enabled` - Whether silence and fillers may be inserted between words.
+    pub fn set_fillers_enabled(&mut self, enabled: bool) -> Result<(), Box<dyn Error>> {
+        let (fillprob, silprob, use_filler) = if enabled {
+            match self.saved_filler_params.take() {
+                Some(saved) => saved,
+                None => return Ok(()),
+            }
+        } else {
+            if self.saved_filler_params.is_none() {
+                let config = self.get_config();
+                self.saved_filler_params = Some((
+                    config.get_float("fillprob")?,
+                    config.get_float("silprob")?,
+                    config.get_bool("fsgusefiller")?,
+                ));
+            }
+            (DISABLED_FILLER_PROB, DISABLED_FILLER_PROB, false)
+        };
+        self.update_search_params(|config| {
+            config.set_float("fillprob", fillprob)?;
+            config.set_float("silprob", silprob)?;
+            config.set_bool("fsgusefiller", use_filler)
+        })
+    }
+
+    /// Set the frame rate of the feature extraction.
+    ///
+    /// The `frate` parameter is updated and the feature extraction is reinitialized with `Decoder::reinit_feat()`.
+    ///
+    /// # Arguments
+    /// - `frate` - Frames per second, must be positive.
+    pub fn set_frame_rate(&mut self, frate: i32) -> Result<(), Box<dyn Error>> {
+        if frate <= 0 {
+            return Err("Frame rate must be positive".into());
+        }
+        self.get_config().set_int("frate", frate as i64)?;
+        self.apply_param_change("frate")
+    }
+
+    /// Get the frame rate of the feature extraction.
+    ///
+    /// # Returns
+    /// Frames per second (the `frate` parameter) or an error if it cannot be read.
+    pub fn get_frame_rate(&self) -> Result<i32, Box<dyn Error>> {
+        Ok(self.get_config().get_int("frate")? as i32)
+    }
+
+    /// Set the length of the analysis window of the feature extraction.
+    ///
+    /// The `wlen` parameter is updated and the feature extraction is reinitialized with `Decoder::reinit_feat()`.
+    ///
+    /// # Arguments
+    /// - `wlen` - Window length in seconds, must be positive.
+    pub fn set_window_length(&mut self, wlen: f64) -> Result<(), Box<dyn Error>> {
+        if !(wlen.is_finite() && wlen > 0.0) {
+            return Err("Window length must be a positive number".into());
+        }
+        self.get_config().set_float("wlen", wlen)?;
+        self.apply_param_change("wlen")
+    }
+
+    /// Change search parameters in the configuration and reinitialize the decoder to pick them up.
+    ///
+    /// Searches and words added at runtime are lost, see [Changing search parameters](Decoder#changing-search-parameters).
+    fn update_search_params<F>(&mut self, update: F) -> Result<(), Box<dyn Error>>
+    where
+        F: FnOnce(&mut Config) -> Result<(), Box<dyn Error>>,
+    {
+        update(&mut self.get_config())?;
+        self.reload()
+    }
+
+    /// Reinitialize the decoder with its current configuration after parameters have been changed in place.
+    fn reload(&mut self) -> Result<(), Box<dyn Error>> {
+        let result = unsafe { pocketsphinx_sys::ps_reinit(self.inner, std::ptr::null_mut()) };
+        self.boundary_symbols.take();
+
+        if result == -1 {
+            Err("Failed to reinitialize decoder".into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Run a closure with a configuration parameter temporarily set to a different value.
+    ///
+    /// The original value is restored after the closure returns, if setting the temporary value fails and if the closure panics.
+    ///
+    /// # Reinitialization
+    /// Only feature extraction parameters (e.g. `cmninit` or `frate`) are applied without a reinitialization, with `Decoder::reinit_feat()`.
+    /// **All other parameters (e.g. `beam` or `lw`) reinitialize the decoder twice, which discards all searches and dictionary words added at runtime**, see [Changing search parameters](Decoder#changing-search-parameters).
+    ///
+    /// ```rust
+    /// let hyp = decoder.with_temporary_param("cmninit", "40,3,-1", |decoder| {
+    ///     decoder.start_utt()?;
+    ///     decoder.process_raw(&audio, false, true)?;
+    ///     decoder.end_utt()?;
+    ///     decoder.get_hyp()
+    /// })??;
+    /// ```
+    ///
+    /// # Arguments
+    /// - `name`  - Name of the parameter.
+    /// - `value` - Temporary value, converted like in `Config::set_str()`.
+    /// - `f`     - Closure to run while the parameter is set.
+    pub fn with_temporary_param<F, T>(
+        &mut self,
+        name: &str,
+        value: &str,
+        f: F,
+    ) -> Result<T, Box<dyn Error>>
+    where
+        F: FnOnce(&mut Self) -> T,
+    {
+        let mut config = self.get_config();
+        let (param_type, _required) = config.get_typeof_param(name)?;
+        let original = match param_type {
+            ParamType::Integer => Some(config.get_int(name)?.to_string()),
+            ParamType::Float => Some(config.get_float(name)?.to_string()),
+            ParamType::Boolean => Some(config.get_bool(name)?.to_string()),
+            // String parameters may be unset
+            ParamType::String => config.get_str(name).ok(),
+        };
+
+        let mut guard = ParamRestoreGuard {
+            decoder: self,
+            name,
+            original: Some(original),
+        };
+        config.set_str(name, value)?;
+        guard.decoder.apply_param_change(name)?;
+        let result = f(guard.decoder);
+        guard.restore()?;
+
+        Ok(result)
+    }
+
+    /// Apply a parameter changed in the configuration of the decoder.
+    fn apply_param_change(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
+        if FEATURE_PARAMS.contains(&name) {
+            let config = self.get_config();
+            self.reinit_feat(&config)
+        } else {
+            self.reload()
+        }
+    }
+
+    /// Get the current cepstral mean as a string.
+    ///
+    /// This can be used to persist the cepstral mean normalization across utterances or decoder instances, e.g. to restore it after `Decoder::reinit_feat()`.
+    ///
+    /// # Arguments
+    /// - `update` - Update the cepstral mean using data processed so far.
+    ///
+    /// # Returns
+    /// Comma-separated values of the cepstral mean.
+    pub fn get_cmn(&mut self, update: bool) -> Result<String, Box<dyn Error>> {
+        let c_cmn = unsafe { pocketsphinx_sys::ps_get_cmn(self.inner, update as i32) };
+        if c_cmn.is_null() {
+            return Err("Failed to get cepstral mean".into());
+        }
+        let cmn = unsafe { std::ffi::CStr::from_ptr(c_cmn) }
+            .to_str()
+            .map_err(|_| "Failed to convert cepstral mean to string")?;
+        Ok(cmn.to_string())
+    }
 
-    // ps_set_cmn
+    /// Set the current cepstral mean from a string, e.g. one returned by `Decoder::get_cmn()`.
+    ///
+    /// # Arguments
+    /// - `cmn` - Comma-separated values of the cepstral mean. Only the first value is required, the others default to zero.
+    pub fn set_cmn(&mut self, cmn: &str) -> Result<(), Box<dyn Error>> {
+        let c_cmn = to_cstring("cmn", cmn)?;
+        let result = unsafe { pocketsphinx_sys::ps_set_cmn(self.inner, c_cmn.as_ptr()) };
+        if result < 0 {
+            Err("Failed to set cepstral mean".into())
+        } else {
+            Ok(())
+        }
+    }
 
     /// Returns a retained decoder and assures the underlying pointer is not freed before the returned decoder is dropped.
     ///
@@ -407,14 +1107,25 @@ impl Decoder {
     /// In general you will not need to use this function, ever.
     /// It is mainly here for the convenience of scripting language bindings.
     ///
+    /// # Safety
+    /// Both handles refer to the same pocketsphinx decoder, which is not thread-safe, and its reference count is not atomic.
+    /// The caller must make sure that the handles are never used or dropped concurrently, e.g. by keeping them on the same thread.
+    ///
     /// # Returns
     /// A new `Decoder` object with the retained underlying pointer.
-    pub fn retain(&mut self) -> Self {
-        let retained_inner = unsafe { pocketsphinx_sys::ps_retain(self.inner) };
-        self.retained = true;
+    pub unsafe fn retain(&self) -> Self {
+        let retained_inner = pocketsphinx_sys::ps_retain(self.inner);
         Self {
             inner: retained_inner,
             retained: false,
+            utt_state: self.utt_state.clone(),
+            utt_samples: self.utt_samples,
+            total_samples: self.total_samples,
+            utt_id: self.utt_id.clone(),
+            saved_filler_params: self.saved_filler_params,
+            allphone_searches: self.allphone_searches.clone(),
+            pending_samples: Vec::new(),
+            boundary_symbols: self.boundary_symbols.clone(),
         }
     }
 
@@ -436,7 +1147,22 @@ impl Decoder {
         LogMath::from_decoder(self)
     }
 
-    // ps_update_mllr
+    /// Adapt the acoustic model with a speaker-adaptive linear transform (MLLR).
+    ///
+    /// The transform replaces any previously applied one. To apply a transform when the decoder is initialized, use `Config::set_mllr()` instead.
+    ///
+    /// # Arguments
+    /// - `mllr` - The transform, e.g. read with `MLLR::read()`. The decoder keeps its own reference to it.
+    pub fn update_mllr(&mut self, mllr: &MLLR) -> Result<(), Box<dyn Error>> {
+        // The decoder takes over the reference it is given and frees it when the transform is replaced
+        let retained_mllr = unsafe { pocketsphinx_sys::ps_mllr_retain(mllr.get_inner()) };
+        let result = unsafe { pocketsphinx_sys::ps_update_mllr(self.inner, retained_mllr) };
+        if result.is_null() {
+            Err("Failed to update MLLR transform".into())
+        } else {
+            Ok(())
+        }
+    }
 
     /// Reload the pronunciation dictionary from a file.
     /// This function replaces the current pronunciation dictionary with the one stored in the given dictionary. This also causes the active search module(s) to be reinitialized, in the same manner as calling add_word() with update=true.
@@ -451,21 +1177,21 @@ impl Decoder {
         fdictfile: Option<&str>,
         format: Option<&str>,
     ) -> Result<(), Box<dyn Error>> {
-        let c_dictfile = std::ffi::CString::new(dictfile)?;
-
-        let c_fdictfile_ptr = if fdictfile.is_none() {
-            std::ptr::null()
-        } else {
-            let c_fdictfile = std::ffi::CString::new(fdictfile.unwrap())?;
-            c_fdictfile.as_ptr()
-        };
-
-        let c_format_ptr = if format.is_none() {
-            std::ptr::null()
-        } else {
-            let c_format = std::ffi::CString::new(format.unwrap())?;
-            c_format.as_ptr()
-        };
+        let c_dictfile = to_cstring("dictfile", dictfile)?;
+
+        let c_fdictfile = fdictfile
+            .map(|fdictfile| to_cstring("fdictfile", fdictfile))
+            .transpose()?;
+        let c_fdictfile_ptr = c_fdictfile
+            .as_ref()
+            .map_or(std::ptr::null(), |c_fdictfile| c_fdictfile.as_ptr());
+
+        let c_format = format
+            .map(|format| to_cstring("format", format))
+            .transpose()?;
+        let c_format_ptr = c_format
+            .as_ref()
+            .map_or(std::ptr::null(), |c_format| c_format.as_ptr());
 
         let result = unsafe {
             pocketsphinx_sys::ps_load_dict(
@@ -475,6 +1201,9 @@ impl Decoder {
                 c_format_ptr,
             )
         };
+        if fdictfile.is_some() {
+            self.boundary_symbols.take();
+        }
 
         if result == -1 {
             Err("Failed to load dictionary".into())
@@ -487,14 +1216,14 @@ impl Decoder {
     ///
     /// This function dumps the current pronunciation dictionary to a text file.
     pub fn save_dict(&self, dictfile: &str, format: Option<&str>) -> Result<(), Box<dyn Error>> {
-        let c_dictfile = std::ffi::CString::new(dictfile)?;
+        let c_dictfile = to_cstring("dictfile", dictfile)?;
 
-        let c_format_ptr = if format.is_none() {
-            std::ptr::null()
-        } else {
-            let c_format = std::ffi::CString::new(format.unwrap())?;
-            c_format.as_ptr()
-        };
+        let c_format = format
+            .map(|format| to_cstring("format", format))
+            .transpose()?;
+        let c_format_ptr = c_format
+            .as_ref()
+            .map_or(std::ptr::null(), |c_format| c_format.as_ptr());
 
         let result = unsafe {
             pocketsphinx_sys::ps_save_dict(self.inner, c_dictfile.as_ptr(), c_format_ptr)
@@ -521,8 +1250,8 @@ impl Decoder {
         phones: &str,
         update: bool,
     ) -> Result<(), Box<dyn Error>> {
-        let c_word = std::ffi::CString::new(word)?;
-        let c_phones = std::ffi::CString::new(phones)?;
+        let c_word = to_cstring("word", word)?;
+        let c_phones = to_cstring("phones", phones)?;
 
         let result = unsafe {
             pocketsphinx_sys::ps_add_word(
@@ -540,26 +1269,142 @@ impl Decoder {
         }
     }
 
-    /// Look up a word in the dictionary and return phone transcription for it.
+    /// Add the words of a dictionary file one by one, collecting the entries that are rejected instead of failing as a whole like `Decoder::load_dict()`.
+    ///
+    /// The words are added to the current dictionary, which is not replaced. Lines starting with `##` or `;;` are treated as comments.
+    /// The active search is rebuilt once after all words have been added, so this can not be used during an utterance.
+    ///
+    /// # Arguments
+    /// - `dictfile` - Path to the dictionary file with one `word phone1 phone2 ...` entry per line.
+    ///
+    /// # Returns
+    /// The number of added words and the rejected entries with the reason or an error if the file cannot be read.
+    pub fn load_dict_collecting(
+        &mut self,
+        dictfile: &str,
+    ) -> Result<DictLoadReport, Box<dyn Error>> {
+        if self.is_utt_active() {
+            return Err("Can not load dictionary words during an utterance".into());
+        }
+        let content = std::fs::read_to_string(dictfile)?;
+        let mut report = DictLoadReport {
+            added: 0,
+            skipped: Vec::new(),
+        };
+
+        let mut seen = std::collections::HashSet::new();
+        let mut entries = Vec::new();
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with("##") || line.starts_with(";;") {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let word = fields.next().unwrap_or_default();
+            let phones = fields.collect::<Vec<_>>().join(" ");
+            let reason = if phones.is_empty() {
+                "Missing pronunciation"
+            } else if !seen.insert(word) || self.lookup_word(word)?.is_some() {
+                "Already in the dictionary"
+            } else {
+                entries.push((word, phones));
+                continue;
+            };
+            report.skipped.push((word.to_string(), reason.to_string()));
+        }
+
+        for (word, phones) in entries {
+            match self.add_word(word, &phones, false) {
+                Ok(()) => report.added += 1,
+                Err(_) => report.skipped.push((
+                    word.to_string(),
+                    format!("Invalid pronunciation {} (unknown phone?)", phones),
+                )),
+            }
+        }
+        if report.added > 0 {
+            self.refresh_active_search()?;
+        }
+        Ok(report)
+    }
+
+    /// Rebuild the active search, so that it picks up words added with `Decoder::add_word()` without `update`.
+    ///
+    /// Only N-Gram and grammar searches map the dictionary words when they are created, other searches are left as they are.
+    fn refresh_active_search(&mut self) -> Result<(), Box<dyn Error>> {
+        let name = self.get_current_search()?;
+        match self.active_search_object() {
+            ActiveSearch::Ngram(lm) => self.add_lm(&name, &lm)?,
+            ActiveSearch::Fsg(mut fsg) => self.add_fsg(&name, &mut fsg)?,
+            ActiveSearch::Kws(_) | ActiveSearch::Other(_) => return Ok(()),
+        }
+        // Replacing the search deactivates it
+        self.set_activate_search(&name)
+    }
+
+    /// Look up a word in the dictionary and return phone transcription for it.
     ///
     /// # Arguments
     /// - `word` - Word string to look up.
     ///
     /// # Returns
-    /// Whitespace-spearated phone string describing the pronunciation of the word or `None` if word is not present in the dictionary. The string is allocated and must be freed by the user.
+    /// Whitespace-spearated phone string describing the pronunciation of the word or `None` if word is not present in the dictionary.
     pub fn lookup_word(&self, word: &str) -> Result<Option<String>, Box<dyn Error>> {
-        let c_word = std::ffi::CString::new(word)?;
+        let c_word = to_cstring("word", word)?;
 
         let c_str = unsafe { pocketsphinx_sys::ps_lookup_word(self.inner, c_word.as_ptr()) };
 
         if c_str.is_null() {
             Ok(None)
         } else {
-            let str = unsafe { std::ffi::CStr::from_ptr(c_str) }.to_str()?;
-            Ok(Some(str.to_string()))
+            let phones = unsafe { std::ffi::CStr::from_ptr(c_str) }
+                .to_str()
+                .map(|str| str.to_string());
+            // The pronunciation is allocated by pocketsphinx (with ckd_alloc, which uses malloc)
+            unsafe { libc::free(c_str as *mut libc::c_void) };
+            Ok(Some(phones?))
+        }
+    }
+
+    /// Check whether a word is present in the dictionary.
+    ///
+    /// The C API has no word ID lookup, so this looks up the pronunciation with `ps_lookup_word()` like `Decoder::lookup_word()` and only discards it instead of returning it.
+    ///
+    /// # Arguments
+    /// - `word` - Word string to look up.
+    ///
+    /// # Returns
+    /// `true` if the word is in the dictionary, `false` otherwise (including words that contain a nul byte).
+    pub fn word_exists(&self, word: &str) -> bool {
+        let c_word = match to_cstring("word", word) {
+            Ok(c_word) => c_word,
+            Err(_) => return false,
+        };
+        let c_str = unsafe { pocketsphinx_sys::ps_lookup_word(self.inner, c_word.as_ptr()) };
+        if c_str.is_null() {
+            false
+        } else {
+            // The pronunciation is allocated by pocketsphinx and only needed for the check
+            unsafe { libc::free(c_str as *mut libc::c_void) };
+            true
         }
     }
 
+    /// Look up all pronunciation variants of a word in the dictionary.
+    ///
+    /// Alternate pronunciations are stored as `word(2)`, `word(3)`, etc. and are probed in order until one is missing.
+    ///
+    /// # Returns
+    /// Whitespace-separated phone strings of all variants, starting with the base pronunciation. Empty if the word is not in the dictionary.
+    pub fn pronunciations(&self, word: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut pronunciations = Vec::new();
+        let mut variant = word.to_string();
+        while let Some(phones) = self.lookup_word(&variant)? {
+            pronunciations.push(phones);
+            variant = format!("{}({})", word, pronunciations.len() + 1);
+        }
+        Ok(pronunciations)
+    }
+
     /// Decode a raw audio file.
     ///
     /// No headers are recognized in this files.
@@ -577,8 +1422,8 @@ impl Decoder {
         rawfile: &str,
         max_samples: Option<i64>,
     ) -> Result<i64, Box<dyn Error>> {
-        let c_rawfile = std::ffi::CString::new(rawfile)?;
-        let c_file = unsafe { libc::fopen(c_rawfile.as_ptr(), "rb".as_ptr() as *const i8) };
+        let c_rawfile = to_cstring("rawfile", rawfile)?;
+        let c_file = unsafe { libc::fopen(c_rawfile.as_ptr(), c"rb".as_ptr()) };
         if c_file.is_null() {
             return Err("Failed to open rawfile".into());
         }
@@ -592,6 +1437,213 @@ impl Decoder {
         Ok(num_samples)
     }
 
+    /// Decode raw audio from a reader as a single utterance.
+    ///
+    /// Audio is assumed to be 16-bit signed PCM in the byte order given by the `-input_endian` configuration parameter.
+    /// The bytes are passed on unchanged, pocketsphinx swaps them if that byte order differs from the one of the host.
+    ///
+    /// # Arguments
+    /// - `reader`      - Source of the raw audio, e.g. a file or a network stream.
+    /// - `max_samples` - Maximum number of samples to read from `reader`, or `None` to read until end-of-file.
+    ///
+    /// # Returns
+    /// Number of samples of audio processed.
+    pub fn decode_raw_reader<R: Read>(
+        &mut self,
+        reader: R,
+        max_samples: Option<i64>,
+    ) -> Result<i64, Box<dyn Error>> {
+        self.decode_raw_chunks(reader, max_samples, None)
+    }
+
+    /// Decode a raw audio file, checking a cancellation flag between chunks.
+    ///
+    /// This works like `Decoder::decode_raw_reader()` but checks `cancel` before each chunk.
+    /// Set the flag from another thread (e.g. a UI) to stop decoding early. The utterance is ended either way, so the hypothesis for the audio processed so far is available afterwards.
+    ///
+    /// # Arguments
+    /// - `rawfile`     - Path to the raw audio file.
+    /// - `max_samples` - Maximum number of samples to read from rawfile, or `None` to read until end-of-file.
+    /// - `cancel`      - Flag that stops decoding once it is set to `true`.
+    ///
+    /// # Returns
+    /// Number of samples of audio processed before end-of-file, `max_samples` or cancellation was reached.
+    pub fn decode_raw_file_cancellable(
+        &mut self,
+        rawfile: &str,
+        max_samples: Option<i64>,
+        cancel: &AtomicBool,
+    ) -> Result<i64, Box<dyn Error>> {
+        let file = std::fs::File::open(rawfile)?;
+        self.decode_raw_chunks(std::io::BufReader::new(file), max_samples, Some(cancel))
+    }
+
+    /// Decode raw audio from a reader in chunks as a single utterance, stopping early once `cancel` is set.
+    fn decode_raw_chunks<R: Read>(
+        &mut self,
+        reader: R,
+        max_samples: Option<i64>,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<i64, Box<dyn Error>> {
+        let max_bytes = match max_samples {
+            Some(max_samples) => max_samples.max(0) as u64 * 2,
+            None => u64::MAX,
+        };
+        let mut reader = reader.take(max_bytes);
+        let chunk_bytes = RAW_CHUNK_SAMPLES * 2;
+        let mut chunk = Vec::with_capacity(chunk_bytes);
+        let mut num_samples = 0;
+
+        self.start_utt()?;
+        while !cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+            chunk.clear();
+            let n_bytes = (&mut reader)
+                .take(chunk_bytes as u64)
+                .read_to_end(&mut chunk)?;
+            // Keep the byte order of the input, ps_process_raw() swaps according to -input_endian
+            let samples: Vec<i16> = chunk
+                .chunks_exact(2)
+                .map(|bytes| i16::from_ne_bytes([bytes[0], bytes[1]]))
+                .collect();
+            if !samples.is_empty() {
+                self.process_raw(&samples, false, false)?;
+                num_samples += samples.len() as i64;
+            }
+            if n_bytes < chunk_bytes {
+                break;
+            }
+        }
+        self.end_utt()?;
+
+        Ok(num_samples)
+    }
+
+    /// Decode a sound file as a single utterance.
+    ///
+    /// The format is determined from the file header: WAV (`RIFF`) and NIST Sphere (`NIST_1A`) files must contain 16-bit mono PCM with the sample rate of the configuration.
+    /// Files with an unknown header are decoded as raw audio with `Decoder::decode_raw_file()`, unless their extension is `.wav`, `.sph` or `.nist`, in which case the file is considered corrupt.
+    ///
+    /// # Arguments
+    /// - `path` - Path to the sound file.
+    ///
+    /// # Returns
+    /// The hypothesis string and path score, or `None` if nothing was recognized.
+    pub fn decode_path(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> Result<Option<(String, i32)>, Box<dyn Error>> {
+        let path = path.as_ref();
+        let mut header = [0u8; 7];
+        let n_bytes = std::fs::File::open(path)?.read(&mut header)?;
+        let header = &header[..n_bytes];
+
+        let decoded = if header.starts_with(b"RIFF") {
+            Some(audio::parse_wav_i16(&std::fs::read(path)?)?)
+        } else if header.starts_with(b"NIST_1A") {
+            Some(audio::parse_nist_i16(&std::fs::read(path)?)?)
+        } else {
+            None
+        };
+
+        match decoded {
+            Some((samples, sample_rate)) => {
+                let config_sample_rate = self.get_config().get_float("samprate")?;
+                if sample_rate as f64 != config_sample_rate {
+                    return Err(format!(
+                        "Sample rate of {} ({} Hz) does not match the configured samprate ({} Hz)",
+                        path.display(),
+                        sample_rate,
+                        config_sample_rate
+                    )
+                    .into());
+                }
+                self.start_utt()?;
+                self.process_raw(&samples, false, true)?;
+                self.end_utt()?;
+            }
+            None => {
+                let extension = path
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .map(|extension| extension.to_ascii_lowercase());
+                if let Some(extension @ ("wav" | "sph" | "nist")) = extension.as_deref() {
+                    return Err(format!(
+                        "{} has a .{} extension but no valid header",
+                        path.display(),
+                        extension
+                    )
+                    .into());
+                }
+                let path = path.to_str().ok_or("Path is not valid UTF-8")?;
+                self.decode_raw_file(path, None)?;
+            }
+        }
+        self.get_hyp()
+    }
+
+    /// Move the decoder to a background thread that endpoints and decodes streamed audio.
+    ///
+    /// Audio is fed with `StreamingHandle::feed()`. Speech segments are detected with an `Endpointer` and decoded as separate utterances, reporting `RecognitionEvent::Partial` while an utterance is in progress and `RecognitionEvent::Final` at its end.
+    /// The decoder must not have an active utterance.
+    ///
+    /// ```rust
+    /// let (handle, events) = decoder.into_streaming();
+    /// handle.feed(&samples)?;
+    /// handle.finish()?;
+    /// for event in events {
+    ///     if let RecognitionEvent::Final(text) = event {
+    ///         println!("{}", text);
+    ///     }
+    /// }
+    /// ```
+    pub fn into_streaming(self) -> (StreamingHandle, Receiver<RecognitionEvent>) {
+        StreamingHandle::spawn(self)
+    }
+
+    /// Get the number of senones (tied states) of the acoustic model.
+    ///
+    /// This is the number of scores per frame expected in senone score dumps (see `Decoder::load_senscr()`).
+    /// The C API has no accessor for the loaded acoustic model, so the count is read from the model definition file (`mdef`).
+    pub fn n_senones(&self) -> Result<i32, Box<dyn Error>> {
+        let mdef = std::fs::read(self.model_file("mdef", "mdef")?)?;
+        read_mdef_n_senones(&mdef)
+    }
+
+    /// Get the number of Gaussian mixture components (densities) per codebook of the acoustic model.
+    ///
+    /// The count is read from the Gaussian means file (`mean`) of the model.
+    pub fn n_mixtures(&self) -> Result<i32, Box<dyn Error>> {
+        let means = std::fs::read(self.model_file("mean", "means")?)?;
+        read_means_n_density(&means)
+    }
+
+    /// Get the path of an acoustic model file from its configuration parameter or from the model directory (`hmm`).
+    fn model_file(&self, param: &str, file_name: &str) -> Result<String, Box<dyn Error>> {
+        let config = self.get_config();
+        if let Ok(path) = config.get_str(param) {
+            return Ok(path);
+        }
+        let hmm = config
+            .get_str("hmm")
+            .map_err(|_| "No acoustic model (hmm) configured")?;
+        Ok(format!("{}/{}", hmm, file_name))
+    }
+
+    /// Get a sink that decodes raw PCM bytes written to it.
+    ///
+    /// This allows piping audio into the decoder with `std::io::copy()`.
+    /// The bytes are interpreted as 16-bit signed PCM in the byte order given by the `-input_endian` configuration parameter, see `Decoder::decode_raw_reader()`.
+    /// An utterance must be started with `Decoder::start_utt()` before writing to the sink.
+    ///
+    /// ```rust
+    /// decoder.start_utt()?;
+    /// std::io::copy(&mut std::io::stdin(), &mut decoder.pcm_sink())?;
+    /// decoder.end_utt()?;
+    /// ```
+    pub fn pcm_sink(&mut self) -> PcmSink<'_> {
+        PcmSink::new(self)
+    }
+
     /// Decode a senone score dump file.
     ///
     /// # Arguments
@@ -600,8 +1652,8 @@ impl Decoder {
     /// # Returns
     /// Number of frames read.
     pub fn decode_senscr_file(&mut self, senscrfile: &str) -> Result<i32, Box<dyn Error>> {
-        let c_senscrfile = std::ffi::CString::new(senscrfile)?;
-        let c_file = unsafe { libc::fopen(c_senscrfile.as_ptr(), "rb".as_ptr() as *const i8) };
+        let c_senscrfile = to_cstring("senscrfile", senscrfile)?;
+        let c_file = unsafe { libc::fopen(c_senscrfile.as_ptr(), c"rb".as_ptr()) };
         if c_file.is_null() {
             return Err("Failed to open senscrfile".into());
         }
@@ -613,6 +1665,40 @@ impl Decoder {
         Ok(num_frames)
     }
 
+    /// Decode senone scores from an in-memory buffer.
+    ///
+    /// This works like `Decoder::decode_senscr_file()`, but reads the senone score dump from memory, e.g. when the scores are computed by another process.
+    ///
+    /// # Arguments
+    /// - `data` - Contents of a senone score dump file.
+    ///
+    /// # Returns
+    /// Number of frames read.
+    pub fn load_senscr(&mut self, data: &[u8]) -> Result<i32, Box<dyn Error>> {
+        if data.is_empty() {
+            return Err("Senone score buffer is empty".into());
+        }
+        // fmemopen() is not available on every platform, so the buffer is passed through a temporary file
+        let path = std::env::temp_dir().join(format!(
+            "pocketsphinx-rs-senscr-{}-{}.sen",
+            std::process::id(),
+            SENSCR_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&path, data)?;
+        let result = match path.to_str() {
+            Some(path) => self.decode_senscr_file(path),
+            None => Err("Temporary directory path is not valid UTF-8".into()),
+        };
+        let _ = std::fs::remove_file(&path);
+
+        let num_frames = result?;
+        if num_frames < 0 {
+            Err("Failed to decode senone scores".into())
+        } else {
+            Ok(num_frames)
+        }
+    }
+
     /// Start processing of the stream of speech.
     #[deprecated(
         since = "0.1.0",
@@ -647,32 +1733,70 @@ impl Decoder {
     /// This function should be called before any utterance data is passed to the decoder.
     /// It marks the start of a new utterance and reinitializes internal data structures.
     pub fn start_utt(&mut self) -> Result<(), Box<dyn Error>> {
-        let _result = unsafe { pocketsphinx_sys::ps_start_utt(self.inner) };
+        let result = unsafe { pocketsphinx_sys::ps_start_utt(self.inner) };
+        if result < 0 {
+            return Err("Failed to start utterance".into());
+        }
+        self.utt_state.started.store(true, Ordering::Relaxed);
+        self.utt_state.active.store(true, Ordering::Relaxed);
+        self.utt_state.aborted.store(false, Ordering::Relaxed);
+        self.utt_samples = 0;
 
         Ok(())
     }
 
+    /// Set an identifier for the current or next utterance.
+    ///
+    /// Pocketsphinx does not track utterance identifiers itself, so the identifier is only stored by this wrapper.
+    /// It is kept for following utterances until it is changed, which allows correlating output with input files when decoding in batches.
+    ///
+    /// # Arguments
+    /// - `id` - Identifier of the utterance, e.g. the name of the input file.
+    pub fn set_utt_id(&mut self, id: &str) {
+        self.utt_id = Some(id.to_string());
+    }
+
+    /// Get the identifier set with `Decoder::set_utt_id()`.
+    ///
+    /// # Returns
+    /// The utterance identifier or `None` if none was set.
+    pub fn utt_id(&self) -> Option<String> {
+        self.utt_id.clone()
+    }
+
+    /// Check whether an utterance is currently being processed.
+    ///
+    /// # Returns
+    /// `true` between calls to `Decoder::start_utt()` and `Decoder::end_utt()`, `false` otherwise.
+    pub fn is_utt_active(&self) -> bool {
+        self.utt_state.active.load(Ordering::Relaxed)
+    }
+
     /// Decode raw audio data.
     ///
     /// # Arguments
     /// - `data`      - Raw audio data.
     /// - `no_search` - If `true`, perform feature extraction but don't do any recognition yet.
-    ///                 This may be necessary if your processor has trouble doing recognition in real-time.
+    ///   This may be necessary if your processor has trouble doing recognition in real-time.
     /// - `full_utt`  - If `true`, this block of data is a full utterance worth of data. This may allow the recognizer to produce more accurate results.
     ///
     /// # Returns
-    /// Number of frames of data searched.
+    /// Number of frames of data searched or an error if no utterance was started with `Decoder::start_utt()`.
     pub fn process_raw(
         &mut self,
         data: &[i16],
         no_search: bool,
         full_utt: bool,
     ) -> Result<i32, Box<dyn Error>> {
+        if !self.is_utt_active() {
+            return Err("No active utterance, call start_utt() before process_raw()".into());
+        }
+
         let result = unsafe {
             pocketsphinx_sys::ps_process_raw(
                 self.inner,
                 data.as_ptr(),
-                data.len() as usize,
+                data.len(),
                 no_search as i32,
                 full_utt as i32,
             )
@@ -681,11 +1805,184 @@ impl Decoder {
         if result == -1 {
             Err("Failed to process raw data".into())
         } else {
+            self.utt_samples += data.len();
+            self.total_samples += data.len();
             Ok(result)
         }
     }
 
-    // ps_process_cep
+    /// Decode a continuous stream, starting and ending utterances at the speech transitions detected by an endpointer.
+    ///
+    /// Samples can be passed in chunks of any size, samples that do not fill a complete endpointer frame are kept until the next call.
+    /// The same endpointer must be used for all calls, and the decoder must not have an active utterance when the stream starts.
+    ///
+    /// # Arguments
+    /// - `endpointer` - Endpointer with the sample rate of the decoder configuration.
+    /// - `samples`    - Next chunk of the audio stream.
+    ///
+    /// # Returns
+    /// The hypotheses of the utterances that ended within this chunk as (text, start, end) with start and end time in seconds. Utterances without a hypothesis are skipped.
+    pub fn feed_continuous(
+        &mut self,
+        endpointer: &Endpointer,
+        samples: &[i16],
+    ) -> Result<Vec<ContinuousResult>, Box<dyn Error>> {
+        let mut pending = std::mem::take(&mut self.pending_samples);
+        pending.extend_from_slice(samples);
+        let frame_size = endpointer.get_frame_size();
+        let n_samples = pending.len() - pending.len() % frame_size;
+
+        let mut results = Vec::new();
+        for frame in pending[..n_samples].chunks_exact(frame_size) {
+            if let Some(speech) = endpointer.process(frame) {
+                if !self.is_utt_active() {
+                    self.start_utt()?;
+                }
+                self.process_raw(speech, false, false)?;
+                if !endpointer.get_in_speech() {
+                    self.end_utt()?;
+                    if let Some((hyp, _score)) = self.get_hyp()? {
+                        results.push((
+                            hyp,
+                            endpointer.get_speech_start(),
+                            endpointer.get_speech_end(),
+                        ));
+                    }
+                }
+            }
+        }
+        pending.drain(..n_samples);
+        self.pending_samples = pending;
+        Ok(results)
+    }
+
+    /// Classify a frame with a voice activity detector and decode it in one call, e.g. to show a listening indicator together with live text.
+    ///
+    /// An utterance is started at the first speech frame if none is active. While an utterance is active, all frames are decoded, so short pauses do not cut words.
+    /// Ending the utterance is left to the caller.
+    ///
+    /// # Arguments
+    /// - `vad`   - Voice activity detector with the sample rate of the decoder configuration.
+    /// - `frame` - Audio frame of exactly `VAD::get_frame_size()` samples.
+    ///
+    /// # Returns
+    /// Whether the frame was classified as speech and the current partial hypothesis, if any.
+    pub fn process_with_vad(
+        &mut self,
+        vad: &mut VAD,
+        frame: &[i16],
+    ) -> Result<(bool, Option<String>), Box<dyn Error>> {
+        if frame.len() != vad.get_frame_size() {
+            return Err(format!(
+                "Expected a frame of {} samples, got {}",
+                vad.get_frame_size(),
+                frame.len()
+            )
+            .into());
+        }
+        let is_speech = match vad.classify(frame) {
+            VADClass::Speech => true,
+            VADClass::NotSpeech => false,
+            VADClass::Error => return Err("Failed to classify frame".into()),
+        };
+
+        if is_speech && !self.is_utt_active() {
+            self.start_utt()?;
+        }
+        if !self.is_utt_active() {
+            return Ok((false, None));
+        }
+        self.process_raw(frame, false, false)?;
+        let partial = self.get_hyp()?.map(|(hyp, _score)| hyp);
+        Ok((is_speech, partial))
+    }
+
+    /// Decode raw audio data and report the partial hypothesis at a fixed interval.
+    ///
+    /// The data is processed in blocks of `every_n_frames` frames and the callback is invoked after each complete block, which avoids the overhead of calling `Decoder::get_hyp()` after every small buffer in tight loops.
+    ///
+    /// # Arguments
+    /// - `data`           - Raw audio data.
+    /// - `every_n_frames` - Number of frames of audio between two invocations of the callback.
+    /// - `cb`             - Callback receiving the current partial hypothesis (empty if there is none yet).
+    ///
+    /// # Returns
+    /// Number of frames of data searched or an error if no utterance was started with `Decoder::start_utt()`.
+    pub fn process_raw_poll(
+        &mut self,
+        data: &[i16],
+        every_n_frames: usize,
+        mut cb: impl FnMut(&str),
+    ) -> Result<i32, Box<dyn Error>> {
+        if every_n_frames == 0 {
+            return Err("Polling interval must be at least one frame".into());
+        }
+        let config = self.get_config();
+        let sample_rate = config.get_float("samprate")?;
+        let frame_rate = config.get_int("frate")? as f64;
+        let block_size = ((sample_rate / frame_rate) as usize * every_n_frames).max(1);
+
+        let mut n_frames = 0;
+        for block in data.chunks(block_size) {
+            n_frames += self.process_raw(block, false, false)?;
+            if block.len() == block_size {
+                let hyp = self.get_hyp()?.map(|(hyp, _score)| hyp).unwrap_or_default();
+                cb(&hyp);
+            }
+        }
+        Ok(n_frames)
+    }
+
+    /// Decode externally computed cepstral features, e.g. MFCCs computed on a DSP.
+    ///
+    /// Every frame must contain `ceplen` coefficients (13 by default) computed with the same parameters (sample rate, filter bank, lifter, ...) as the features the acoustic model was trained on, otherwise the results are meaningless.
+    ///
+    /// # Arguments
+    /// - `mfc`       - Cepstral frames in order of time.
+    /// - `no_search` - If `true`, perform feature extraction but don't do any recognition yet. This may be necessary if your processor has trouble doing recognition in real-time.
+    /// - `full_utt`  - If `true`, this block of data is a full utterance worth of data. This may allow the recognizer to produce more accurate results.
+    ///
+    /// # Returns
+    /// Number of frames of data searched or an error if a frame has the wrong dimension or no utterance is active.
+    pub fn process_cep(
+        &mut self,
+        mfc: &[&[f32]],
+        no_search: bool,
+        full_utt: bool,
+    ) -> Result<i32, Box<dyn Error>> {
+        if !self.is_utt_active() {
+            return Err("No active utterance, call start_utt() before process_cep()".into());
+        }
+        let ceplen = self.get_config().get_int("ceplen")? as usize;
+        if let Some(frame) = mfc.iter().find(|frame| frame.len() != ceplen) {
+            return Err(format!(
+                "Expected frames of {} coefficients, got {}",
+                ceplen,
+                frame.len()
+            )
+            .into());
+        }
+
+        // Copy the frames, since the C API takes mutable pointers
+        let mut frames: Vec<Vec<f32>> = mfc.iter().map(|frame| frame.to_vec()).collect();
+        let mut frame_ptrs: Vec<*mut f32> =
+            frames.iter_mut().map(|frame| frame.as_mut_ptr()).collect();
+        let result = unsafe {
+            pocketsphinx_sys::ps_process_cep(
+                self.inner,
+                frame_ptrs.as_mut_ptr(),
+                frame_ptrs.len() as i32,
+                no_search as i32,
+                full_utt as i32,
+            )
+        };
+
+        if result < 0 {
+            Err("Failed to process cepstral data".into())
+        } else {
+            Ok(result)
+        }
+    }
 
     /// Get the number of frames of data searched.
     ///
@@ -698,18 +1995,95 @@ impl Decoder {
         unsafe { pocketsphinx_sys::ps_get_n_frames(self.inner) }
     }
 
+    /// Get the total duration of audio processed by this decoder.
+    ///
+    /// # Returns
+    /// Seconds of audio passed to `Decoder::process_raw()` across all utterances, based on the configured sample rate.
+    pub fn audio_duration_sec(&self) -> f64 {
+        let sample_rate = self.get_config().get_float("samprate").unwrap_or(16000.0);
+        self.total_samples as f64 / sample_rate
+    }
+
+    /// Get the number of frames which have been input in the current utterance but not searched yet.
+    ///
+    /// This is the delay described in `Decoder::get_n_frames()`, real-time applications can use it to report or compensate for the decoder latency.
+    ///
+    /// # Returns
+    /// Difference between the frames of audio passed to `Decoder::process_raw()` and the frames searched so far.
+    pub fn latency_frames(&self) -> i32 {
+        let config = self.get_config();
+        let sample_rate = config.get_float("samprate").unwrap_or(16000.0);
+        let frame_rate = config.get_int("frate").unwrap_or(100) as f64;
+        let input_frames = (self.utt_samples as f64 * frame_rate / sample_rate) as i32;
+        (input_frames - self.get_n_frames()).max(0)
+    }
+
+    /// Get the number of frames of data searched so far, surfacing failures as an error.
+    ///
+    /// # Returns
+    /// Number of frames of speech data which have been recognized so far in the current or last utterance (zero if no audio has been processed).
+    /// An error if no utterance has been started yet or the decoder reported a failure.
+    pub fn try_n_frames(&self) -> Result<i32, Box<dyn Error>> {
+        if !self.utt_state.started.load(Ordering::Relaxed) {
+            return Err("No utterance has been started".into());
+        }
+        let n_frames = self.get_n_frames();
+        if n_frames < 0 {
+            Err("Failed to get number of frames".into())
+        } else {
+            Ok(n_frames)
+        }
+    }
+
     /// End utterance processing.
     pub fn end_utt(&mut self) -> Result<(), Box<dyn Error>> {
-        let _result = unsafe { pocketsphinx_sys::ps_end_utt(self.inner) };
+        let result = unsafe { pocketsphinx_sys::ps_end_utt(self.inner) };
+        if result < 0 {
+            return Err("Failed to end utterance".into());
+        }
+        self.utt_state.active.store(false, Ordering::Relaxed);
 
         Ok(())
     }
 
+    /// End the current utterance and discard its results.
+    ///
+    /// Unlike `Decoder::end_utt()`, no results are available afterwards: `Decoder::get_hyp()`, `Decoder::get_seg_iter()`, `Decoder::get_nbest_iter()`, `Decoder::get_lattice()` and `Decoder::get_alignment()` return `None` until a new utterance is started with `Decoder::start_utt()`.
+    /// This applies to all handles of the decoder (see `Decoder::retain()`).
+    /// This is useful when the user cancels in the middle of speaking, e.g. in push-to-talk interfaces.
+    pub fn abort_utterance(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.is_utt_active() {
+            self.end_utt()?;
+        }
+        self.utt_state.aborted.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// End the current utterance (if any) and start a new one.
+    ///
+    /// Very long continuous speech makes the search state grow, restarting the utterance from time to time bounds memory and latency of always-on decoders.
+    /// Note that the hypothesis of the ended utterance is discarded, so retrieve it with `Decoder::get_hyp()` before calling this if it is needed.
+    pub fn restart_utterance(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.is_utt_active() {
+            self.end_utt()?;
+        }
+        self.start_utt()
+    }
+
+    /// Check whether the last utterance was ended with `Decoder::abort_utterance()`, in which case its results are hidden.
+    fn is_utt_aborted(&self) -> bool {
+        self.utt_state.aborted.load(Ordering::Relaxed)
+    }
+
     /// Get hypothesis string and path score.
     ///
     /// # Returns
-    /// (hypothesis, score) - Tuple containing the hypothesis string and path score or `None` if no hypothesis is available.
+    /// (hypothesis, score) - Tuple containing the hypothesis string and path score or `Ok(None)` if no hypothesis is available (e.g. the audio contained no speech).
+    /// An error is only returned if the hypothesis can not be converted to a string.
     pub fn get_hyp(&self) -> Result<Option<(String, i32)>, Box<dyn Error>> {
+        if self.is_utt_aborted() {
+            return Ok(None);
+        }
         let mut score = 0;
         let c_str = unsafe { pocketsphinx_sys::ps_get_hyp(self.inner, &mut score) };
 
@@ -720,8 +2094,67 @@ impl Decoder {
                 .to_str()
                 .map_err(|_| "Failed to convert hypothesis to string")?;
 
-            Ok(Some((str.to_string(), score)))
+            // Searches report an empty hypothesis if nothing but silence or fillers was recognized
+            if str.trim().is_empty() {
+                Ok(None)
+            } else {
+                Ok(Some((str.to_string(), score)))
+            }
+        }
+    }
+
+    /// Get hypothesis string and path score together with whether it is final.
+    ///
+    /// # Returns
+    /// A tuple of the hypothesis, its path score and `true` if the utterance has been ended with `Decoder::end_utt()` or `false` for a partial hypothesis.
+    /// `None` if no hypothesis is available or no utterance has been started yet.
+    pub fn get_hyp_status(&self) -> Result<Option<HypothesisStatus>, Box<dyn Error>> {
+        if !self.utt_state.started.load(Ordering::Relaxed) {
+            return Ok(None);
         }
+        let is_final = !self.is_utt_active();
+        Ok(self.get_hyp()?.map(|(hyp, score)| (hyp, score, is_final)))
+    }
+
+    /// Get the hypothesis with its tokens joined without spaces.
+    ///
+    /// This is meant for character based models of languages which are not delimited by spaces (e.g. Chinese or Japanese), where every "word" is a character.
+    /// Fillers and alternate pronunciation markers are removed.
+    ///
+    /// # Returns
+    /// The joined hypothesis or `None` if no hypothesis is available.
+    pub fn get_hyp_chars(&self) -> Option<String> {
+        let symbols = self.boundary_symbols();
+        let seg_iter = self.get_seg_iter()?;
+        let hyp = seg_iter
+            .map(|seg| seg.get_word())
+            .filter(|word| !symbols.is_filler(word))
+            .map(|word| strip_alternate_pronunciation(&word).to_string())
+            .collect();
+        Some(hyp)
+    }
+
+    /// Get the acoustic and language model scores that make up the path score of the best hypothesis.
+    ///
+    /// The scores are summed over all segments of the hypothesis.
+    /// Note that the language model scores of the segments are not weighted, so `acoustic + lm` only approximates `total`.
+    ///
+    /// # Returns
+    /// The score breakdown or `None` if no hypothesis is available.
+    pub fn score_breakdown(&self) -> Option<ScoreBreakdown> {
+        let (_hyp, total) = self.get_hyp().ok()??;
+        let mut acoustic = 0;
+        let mut lm = 0;
+        for seg in self.get_seg_iter()? {
+            let prob = seg.get_prob();
+            acoustic += prob.am_score;
+            lm += prob.lm_score;
+        }
+        Some(ScoreBreakdown {
+            acoustic,
+            lm,
+            total,
+        })
     }
 
     /// Get posterior probability.
@@ -731,27 +2164,126 @@ impl Decoder {
     /// Ongoing research into effective confidence annotation for partial hypotheses may result in these restrictions being lifted in future versions.
     ///
     /// # Returns
-    /// Posterior probability of the best hypothesis.
+    /// Posterior probability of the best hypothesis, or the log-zero value of the decoder's `LogMath` after `Decoder::abort_utterance()`.
     pub fn get_prob(&self) -> i32 {
+        if self.is_utt_aborted() {
+            return self.get_logmath().get_zero();
+        }
         unsafe { pocketsphinx_sys::ps_get_prob(self.inner) }
     }
 
-    /// ps_get_lattice
+    /// Get posterior probability, only if it is meaningful.
+    ///
+    /// Unlike `Decoder::get_prob()`, this does not return a bogus zero when the -bestpath option is disabled.
+    ///
+    /// # Returns
+    /// Posterior probability of the best hypothesis or `None` if -bestpath is disabled.
+    pub fn get_posterior(&self) -> Option<i32> {
+        if self.bestpath_enabled() {
+            Some(self.get_prob())
+        } else {
+            None
+        }
+    }
+
+    /// Check whether the -bestpath option is enabled.
+    ///
+    /// Posterior probabilities and confidence scores are only computed if it is.
+    pub fn bestpath_enabled(&self) -> bool {
+        self.get_config().get_bool("bestpath").unwrap_or(false)
+    }
+
+    /// Get the word lattice object for the current utterance.
+    ///
+    /// # Returns
+    /// Word lattice for the current utterance, or `None` if the current search does not produce one.
+    /// The decoder owns the lattice and it is invalidated when the next utterance is started.
+    pub fn get_lattice(&self) -> Option<Lattice> {
+        if self.is_utt_aborted() {
+            return None;
+        }
+        Lattice::from_decoder(self)
+    }
 
     /// Get an iterator over the word segmentation for the best hypothesis.
     ///
     /// # Returns
-    /// Iterator over the best hypothesis at this point in decoding. None if no hypothesis is available.
-    pub fn get_seg_iter(&self) -> Option<SegIter> {
+    /// Iterator over the best hypothesis at this point in decoding. `None` if no hypothesis is available (e.g. before decoding or after `Decoder::abort_utterance()`), which is not an error.
+    pub fn get_seg_iter(&self) -> Option<SegIter<'_>> {
+        if self.is_utt_aborted() {
+            return None;
+        }
         SegIter::from_decoder(self)
     }
 
+    /// Write the current hypothesis as SubRip (SRT) subtitles with one cue per word.
+    ///
+    /// Timestamps are derived from the segment frames and the `frate` of the decoder configuration.
+    /// Fillers such as silence and noise are not written.
+    ///
+    /// # Arguments
+    /// - `w` - Writer receiving the subtitles.
+    pub fn write_srt<W: Write>(&self, w: &mut W) -> Result<(), Box<dyn Error>> {
+        let cues = subtitle::word_cues(self)?;
+        subtitle::write_srt(w, &cues)?;
+        Ok(())
+    }
+
+    /// Write the current hypothesis as WebVTT subtitles with one cue per word.
+    ///
+    /// Like `Decoder::write_srt()`, but with a `WEBVTT` header and `HH:MM:SS.mmm` timestamps as used for `<track>` captions in browsers.
+    ///
+    /// # Arguments
+    /// - `w` - Writer receiving the subtitles.
+    pub fn write_vtt<W: Write>(&self, w: &mut W) -> Result<(), Box<dyn Error>> {
+        let cues = subtitle::word_cues(self)?;
+        subtitle::write_vtt(w, &cues)?;
+        Ok(())
+    }
+
     /// Get an iterator over the best hypotheses.
-    /// The function may return `None` which means that there is no hypothesis available for this utterance.
+    /// The function may return `None` which means that there is no hypothesis available for this utterance. This is not an error, e.g. the search may not support N-best lists.
     pub fn get_nbest_iter(&self) -> Option<NBestIter> {
+        if self.is_utt_aborted() {
+            return None;
+        }
         NBestIter::from_decoder(self)
     }
 
+    /// Get the top N-best hypotheses together with their posterior probabilities.
+    ///
+    /// The posteriors are computed by normalizing the path scores over the returned hypotheses, so they sum up to one.
+    /// For meaningful scores the `bestpath` search pass should be enabled.
+    ///
+    /// # Arguments
+    /// - `n` - Maximum number of hypotheses.
+    ///
+    /// # Returns
+    /// The hypotheses sorted by descending posterior. Empty if no hypothesis is available.
+    pub fn nbest_with_posteriors(&self, n: usize) -> Vec<NBestResult> {
+        let hypotheses: Vec<NBestHypothesis> = match self.get_nbest_iter() {
+            Some(nbest_iter) => nbest_iter.take_hypotheses(n),
+            None => return Vec::new(),
+        };
+
+        let logmath = self.get_logmath();
+        let total = hypotheses
+            .iter()
+            .map(|hyp| hyp.score)
+            .reduce(|total, score| logmath.add(total, score))
+            .unwrap_or_default();
+        let mut results: Vec<NBestResult> = hypotheses
+            .into_iter()
+            .map(|hyp| NBestResult {
+                posterior: logmath.exp(hyp.score - total).clamp(0.0, 1.0),
+                hypothesis: hyp.hypothesis,
+                score: hyp.score,
+            })
+            .collect();
+        results.sort_by(|a, b| b.posterior.total_cmp(&a.posterior));
+        results
+    }
+
     /// Get performance information for the current utterance.
     pub fn get_utt_time(&self) -> DecoderPerformanceInfo {
         let mut speech = 0.0;
@@ -773,8 +2305,139 @@ impl Decoder {
     pub fn get_inner(&self) -> *mut pocketsphinx_sys::ps_decoder_t {
         self.inner
     }
+
+    /// Take ownership of a raw decoder pointer.
+    ///
+    /// # Safety
+    /// `ptr` must be a valid decoder (e.g. returned by `ps_init()` or `Decoder::into_raw()`) and the caller must own one reference to it, which is released when the returned `Decoder` is dropped.
+    /// Other handles to the same decoder must not be used or dropped concurrently with the returned `Decoder`, see `Decoder::retain()`.
+    ///
+    /// The utterance state is not carried over from other handles, so the decoder must not be in an active utterance.
+    /// Otherwise `Decoder::start_utt()` fails until the utterance is ended with `ps_end_utt()`.
+    pub unsafe fn from_raw(ptr: *mut pocketsphinx_sys::ps_decoder_t) -> Self {
+        Self {
+            inner: ptr,
+            retained: false,
+            utt_state: Arc::default(),
+            utt_samples: 0,
+            total_samples: 0,
+            utt_id: None,
+            saved_filler_params: None,
+            allphone_searches: Vec::new(),
+            pending_samples: Vec::new(),
+            boundary_symbols: RefCell::default(),
+        }
+    }
+
+    /// Convert the decoder into a raw pointer without freeing it.
+    ///
+    /// The caller owns one reference to the returned decoder and is responsible for releasing it, either with `ps_free()` or by converting it back with `Decoder::from_raw()`.
+    /// An active utterance should be ended before, since `Decoder::from_raw()` can not restore the utterance state.
+    pub fn into_raw(mut self) -> *mut pocketsphinx_sys::ps_decoder_t {
+        let inner = if self.retained {
+            // This wrapper does not own a reference, so hand out a new one
+            unsafe { pocketsphinx_sys::ps_retain(self.inner) }
+        } else {
+            self.inner
+        };
+        // Release the Rust-side state that would otherwise be leaked by forgetting self
+        self.utt_id = None;
+        self.pending_samples = Vec::new();
+        std::mem::forget(self);
+        inner
+    }
 }
 
+/// Check the result of a function adding a search, which returns 0 on success and -1 on failure.
+///
+/// # Arguments
+/// - `result`  - Return value of the C function.
+/// - `message` - Creates the error message, the result code is appended to it.
+fn check_search_result<F>(result: i32, message: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce() -> String,
+{
+    if result < 0 {
+        Err(format!("{} (error code {})", message(), result).into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Read the number of senones from a text or binary model definition file.
+fn read_mdef_n_senones(mdef: &[u8]) -> Result<i32, Box<dyn Error>> {
+    if mdef.len() >= 12 && (&mdef[0..4] == b"BMDF" || &mdef[0..4] == b"FDMB") {
+        let swap = &mdef[0..4] == b"FDMB";
+        let read_i32 = |offset: usize| -> Result<i32, Box<dyn Error>> {
+            let bytes: [u8; 4] = mdef
+                .get(offset..offset + 4)
+                .ok_or("Truncated binary model definition")?
+                .try_into()?;
+            let value = i32::from_le_bytes(bytes);
+            Ok(if swap { value.swap_bytes() } else { value })
+        };
+        // Magic, version and the length of the padded format description precede the counts
+        let desc_len = read_i32(8)?.max(0) as usize;
+        // Counts: n_ciphone, n_phone, n_emit_state, n_ci_sen, n_sen
+        return read_i32(12 + desc_len + 4 * 4);
+    }
+
+    String::from_utf8_lossy(mdef)
+        .lines()
+        .find_map(
+            |line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [count, "n_tied_state"] => count.parse().ok(),
+                _ => None,
+            },
+        )
+        .ok_or_else(|| "Model definition has no n_tied_state".into())
+}
+
+/// Read the number of densities per codebook from a Sphinx-3 Gaussian parameter file.
+fn read_means_n_density(means: &[u8]) -> Result<i32, Box<dyn Error>> {
+    let end_header = b"endhdr\n";
+    let data_start = means
+        .windows(end_header.len())
+        .position(|window| window == end_header)
+        .ok_or("Gaussian parameter file has no header")?
+        + end_header.len();
+    let read_u32 = |offset: usize| -> Result<u32, Box<dyn Error>> {
+        let bytes: [u8; 4] = means
+            .get(data_start + offset..data_start + offset + 4)
+            .ok_or("Truncated Gaussian parameter file")?
+            .try_into()?;
+        Ok(u32::from_le_bytes(bytes))
+    };
+    // The byte order marker is followed by the number of codebooks, features and densities
+    let swap = match read_u32(0)? {
+        0x11223344 => false,
+        0x44332211 => true,
+        _ => return Err("Invalid byte order marker in Gaussian parameter file".into()),
+    };
+    let n_density = read_u32(12)?;
+    Ok(if swap {
+        n_density.swap_bytes()
+    } else {
+        n_density
+    } as i32)
+}
+
+impl TryFrom<Config> for Decoder {
+    type Error = Box<dyn Error>;
+
+    /// Initialize the decoder from a configuration, taking ownership of it.
+    ///
+    /// The decoder keeps its own reference to the configuration, which can be accessed with `Decoder::get_config()`.
+    fn try_from(config: Config) -> Result<Self, Self::Error> {
+        // The decoder retains the configuration, so dropping `config` afterwards only releases the caller's reference
+        Self::init(config.get_inner())
+    }
+}
+
+// The decoder is not thread-safe, but a `Decoder` created in safe code is the only handle to its pocketsphinx decoder.
+// Additional handles can only be created with the unsafe `Decoder::retain()` and `Decoder::from_raw()`, whose callers must not use them across threads.
+unsafe impl Send for Decoder {}
+
 impl Drop for Decoder {
     fn drop(&mut self) {
         if !self.retained {
@@ -785,6 +2448,98 @@ impl Drop for Decoder {
     }
 }
 
+/// Restores a parameter changed by `Decoder::with_temporary_param()` when dropped, e.g. while unwinding from a panic in the closure.
+struct ParamRestoreGuard<'a> {
+    decoder: &'a mut Decoder,
+    name: &'a str,
+    /// Original value of the parameter, `None` once it has been restored.
+    original: Option<Option<String>>,
+}
+
+impl ParamRestoreGuard<'_> {
+    fn restore(&mut self) -> Result<(), Box<dyn Error>> {
+        let original = match self.original.take() {
+            Some(original) => original,
+            None => return Ok(()),
+        };
+        let mut config = self.decoder.get_config();
+        match original {
+            Some(original) => config.set_str(self.name, &original)?,
+            None => {
+                let c_name = to_cstring("name", self.name)?;
+                unsafe {
+                    pocketsphinx_sys::ps_config_set_str(
+                        config.get_inner(),
+                        c_name.as_ptr(),
+                        std::ptr::null(),
+                    )
+                };
+            }
+        }
+        self.decoder.apply_param_change(self.name)
+    }
+}
+
+impl Drop for ParamRestoreGuard<'_> {
+    fn drop(&mut self) {
+        // Errors can only be reported by restore()
+        let _ = self.restore();
+    }
+}
+
+/// Searches of a decoder captured with `Decoder::save_state()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecoderState {
+    /// Names of all searches.
+    pub searches: Vec<String>,
+    /// Name of the active search.
+    pub active_search: Option<String>,
+}
+
+/// Results of a single decoding pass, see `Decoder::decode_full()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeFull {
+    /// Best hypothesis and its path score, or `None` if nothing was recognized.
+    pub best: Option<(String, i32)>,
+    /// Word segmentation of the best hypothesis, including fillers.
+    pub segments: Vec<WordSegment>,
+    /// Alternative hypotheses in N-best order.
+    pub nbest: Vec<NBestHypothesis>,
+}
+
+/// Outcome of `Decoder::load_dict_collecting()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DictLoadReport {
+    /// Number of words added to the dictionary.
+    pub added: usize,
+    /// Rejected entries as (word, reason).
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Pruning beam widths of the search, see `Decoder::get_beam()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BeamSettings {
+    /// Beam width applied to every frame.
+    pub beam: f64,
+    /// Beam width applied to phone transitions.
+    pub pbeam: f64,
+    /// Beam width applied to word exits.
+    pub wbeam: f64,
+    /// Beam width applied to last phone in words.
+    pub lpbeam: f64,
+}
+
+/// Scores making up the path score of a hypothesis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScoreBreakdown {
+    /// Sum of the acoustic model scores of all segments.
+    pub acoustic: i32,
+    /// Sum of the language model scores of all segments.
+    pub lm: i32,
+    /// Total path score of the hypothesis.
+    pub total: i32,
+}
+
 #[derive(Debug)]
 pub struct DecoderPerformanceInfo {
     /// Number of seconds of speech.
@@ -794,3 +2549,907 @@ pub struct DecoderPerformanceInfo {
     /// Number of seconds of wall time used.
     pub wall: f64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        strip_alternate_pronunciation, ActiveSearch, BeamSettings, Config, Decoder, Endpointer,
+        SearchKind, VAD,
+    };
+    use crate::test_util::{
+        decode_utterance, default_decoder, goforward_raw, goforward_samples, swap_bytes, temp_path,
+        GOFORWARD_RAW_PATH, GOFORWARD_TEXT,
+    };
+    use crate::vad::VADMode;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn decode_raw_reader_little_endian() {
+        let raw = goforward_raw();
+        let mut decoder = default_decoder(&[("input_endian", "little")]);
+        let num_samples = decoder.decode_raw_reader(raw.as_slice(), None).unwrap();
+        assert_eq!(num_samples as usize, raw.len() / 2);
+        let (hyp, _score) = decoder.get_hyp().unwrap().unwrap();
+        assert_eq!(hyp, GOFORWARD_TEXT);
+    }
+
+    #[test]
+    fn decode_raw_reader_big_endian() {
+        let raw = swap_bytes(&goforward_raw());
+        let mut decoder = default_decoder(&[("input_endian", "big")]);
+        let num_samples = decoder.decode_raw_reader(raw.as_slice(), None).unwrap();
+        assert_eq!(num_samples as usize, raw.len() / 2);
+        let (hyp, _score) = decoder.get_hyp().unwrap().unwrap();
+        assert_eq!(hyp, GOFORWARD_TEXT);
+    }
+
+    #[test]
+    fn decode_raw_reader_max_samples() {
+        let raw = goforward_raw();
+        let mut decoder = default_decoder(&[]);
+        let num_samples = decoder
+            .decode_raw_reader(raw.as_slice(), Some(1000))
+            .unwrap();
+        assert_eq!(num_samples, 1000);
+    }
+
+    #[test]
+    fn retained_decoder_shares_utterance_state() {
+        let mut decoder = default_decoder(&[]);
+        decoder.start_utt().unwrap();
+        let mut retained = unsafe { decoder.retain() };
+        assert!(retained.is_utt_active());
+        retained.end_utt().unwrap();
+        assert!(!decoder.is_utt_active());
+    }
+
+    #[test]
+    fn from_raw_starts_without_utterance() {
+        let mut decoder = default_decoder(&[]);
+        decoder.start_utt().unwrap();
+        decoder.end_utt().unwrap();
+        let mut decoder = unsafe { Decoder::from_raw(decoder.into_raw()) };
+        assert!(!decoder.is_utt_active());
+        decoder.start_utt().unwrap();
+        assert!(decoder.is_utt_active());
+    }
+
+    #[test]
+    fn utterance_state_follows_return_codes() {
+        let mut decoder = default_decoder(&[]);
+        assert!(decoder.end_utt().is_err());
+        assert!(!decoder.is_utt_active());
+        decoder.start_utt().unwrap();
+        assert!(decoder.start_utt().is_err());
+        assert!(decoder.is_utt_active());
+        decoder.end_utt().unwrap();
+        assert!(!decoder.is_utt_active());
+    }
+
+    #[test]
+    fn hyp_status_without_utterance() {
+        let decoder = default_decoder(&[]);
+        assert_eq!(decoder.get_hyp_status().unwrap(), None);
+    }
+
+    #[test]
+    fn hyp_status_is_final_after_end_utt() {
+        let raw = goforward_raw();
+        let mut decoder = default_decoder(&[]);
+        decoder.decode_raw_reader(raw.as_slice(), None).unwrap();
+        let (hyp, _score, is_final) = decoder.get_hyp_status().unwrap().unwrap();
+        assert_eq!(hyp, GOFORWARD_TEXT);
+        assert!(is_final);
+    }
+
+    #[test]
+    fn temporary_param_is_restored() {
+        let mut decoder = default_decoder(&[]);
+        let original = decoder.get_config().get_float("beam").unwrap();
+        let temporary = decoder
+            .with_temporary_param("beam", "1e-20", |decoder| {
+                decoder.get_config().get_float("beam").unwrap()
+            })
+            .unwrap();
+        assert_eq!(temporary, 1e-20);
+        assert_eq!(decoder.get_config().get_float("beam").unwrap(), original);
+    }
+
+    #[test]
+    fn temporary_param_is_restored_after_panic() {
+        let mut decoder = default_decoder(&[]);
+        let original = decoder.get_config().get_str("cmninit").unwrap();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            decoder.with_temporary_param("cmninit", "40,3,-1", |_| panic!("closure panicked"))
+        }));
+        assert!(result.is_err());
+        assert_eq!(decoder.get_config().get_str("cmninit").unwrap(), original);
+    }
+
+    #[test]
+    fn abort_utterance_hides_results() {
+        let samples = goforward_samples();
+        let mut decoder = default_decoder(&[]);
+        decoder.start_utt().unwrap();
+        decoder.process_raw(&samples, false, false).unwrap();
+        decoder.abort_utterance().unwrap();
+
+        assert!(!decoder.is_utt_active());
+        assert_eq!(decoder.get_hyp().unwrap(), None);
+        assert!(decoder.get_seg_iter().is_none());
+        assert!(decoder.get_nbest_iter().is_none());
+        assert!(decoder.get_lattice().is_none());
+        assert!(decoder.score_breakdown().is_none());
+        let retained = unsafe { decoder.retain() };
+        assert_eq!(retained.get_hyp().unwrap(), None);
+        drop(retained);
+
+        decoder.start_utt().unwrap();
+        decoder.process_raw(&samples, false, true).unwrap();
+        decoder.end_utt().unwrap();
+        let (hyp, _score) = decoder.get_hyp().unwrap().unwrap();
+        assert_eq!(hyp, GOFORWARD_TEXT);
+    }
+
+    #[test]
+    fn set_lw_changes_scores() {
+        let samples = goforward_samples();
+        let mut decoder = default_decoder(&[]);
+        let (_hyp, score) = decode_utterance(&mut decoder, &samples).unwrap();
+
+        let lw = decoder.get_lw().unwrap() * 2.0;
+        decoder.set_lw(lw).unwrap();
+        assert_eq!(decoder.get_lw().unwrap(), lw);
+        let (_hyp, weighted_score) = decode_utterance(&mut decoder, &samples).unwrap();
+        assert_ne!(score, weighted_score);
+        assert!(decoder.set_lw(0.0).is_err());
+    }
+
+    #[test]
+    fn set_wip_changes_lm_scores() {
+        let samples = goforward_samples();
+        let lm_scores = |decoder: &Decoder| -> Vec<i32> {
+            decoder
+                .get_seg_iter()
+                .unwrap()
+                .map(|seg| seg.get_prob().lm_score)
+                .collect()
+        };
+        let mut decoder = default_decoder(&[]);
+        decode_utterance(&mut decoder, &samples).unwrap();
+        let scores = lm_scores(&decoder);
+
+        decoder.set_wip(0.5).unwrap();
+        assert_eq!(decoder.get_wip().unwrap(), 0.5);
+        decode_utterance(&mut decoder, &samples).unwrap();
+        assert_ne!(scores, lm_scores(&decoder));
+    }
+
+    #[test]
+    fn get_beam_matches_config() {
+        let mut decoder = default_decoder(&[("beam", "1e-40"), ("wbeam", "1e-30")]);
+        let beams = decoder.get_beam().unwrap();
+        let config = decoder.get_config();
+        assert_eq!(beams.beam, config.get_float("beam").unwrap());
+        assert_eq!(beams.pbeam, config.get_float("pbeam").unwrap());
+        assert_eq!(beams.wbeam, config.get_float("wbeam").unwrap());
+        assert_eq!(beams.lpbeam, config.get_float("lpbeam").unwrap());
+
+        let narrow = BeamSettings {
+            beam: 1e-20,
+            ..beams
+        };
+        decoder.set_beam(&narrow).unwrap();
+        assert_eq!(decoder.get_beam().unwrap(), narrow);
+    }
+
+    #[test]
+    fn set_fillers_enabled_restores_configured_probabilities() {
+        let samples = goforward_samples();
+        let mut decoder = default_decoder(&[("fillprob", "1e-4"), ("silprob", "0.1")]);
+
+        decoder.set_fillers_enabled(false).unwrap();
+        decode_utterance(&mut decoder, &samples).unwrap();
+        let words: Vec<String> = decoder
+            .get_seg_iter()
+            .unwrap()
+            .map(|seg| seg.get_word())
+            .collect();
+        assert!(!words
+            .iter()
+            .any(|word| word == "<sil>" || word.starts_with('[') || word.starts_with("++")));
+
+        decoder.set_fillers_enabled(true).unwrap();
+        let config = decoder.get_config();
+        assert_eq!(config.get_float("fillprob").unwrap(), 1e-4);
+        assert_eq!(config.get_float("silprob").unwrap(), 0.1);
+        assert!(config.get_bool("fsgusefiller").unwrap());
+    }
+
+    #[test]
+    fn load_senscr_round_trip() {
+        let senlogdir =
+            std::env::temp_dir().join(format!("pocketsphinx-rs-senlog-{}", std::process::id()));
+        std::fs::create_dir_all(&senlogdir).unwrap();
+        let mut logging_decoder = default_decoder(&[("senlogdir", senlogdir.to_str().unwrap())]);
+        decode_utterance(&mut logging_decoder, &goforward_samples()).unwrap();
+        let n_frames = logging_decoder.get_n_frames();
+        drop(logging_decoder);
+        let dump_path = std::fs::read_dir(&senlogdir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let dump = std::fs::read(dump_path).unwrap();
+        std::fs::remove_dir_all(&senlogdir).unwrap();
+
+        let mut decoder = default_decoder(&[]);
+        assert_eq!(decoder.load_senscr(&dump).unwrap(), n_frames);
+        let (hyp, _score) = decoder.get_hyp().unwrap().unwrap();
+        assert_eq!(hyp, GOFORWARD_TEXT);
+        assert!(decoder.load_senscr(&[]).is_err());
+    }
+
+    #[test]
+    fn load_dict_collecting_reports_bad_phones() {
+        let dictfile =
+            std::env::temp_dir().join(format!("pocketsphinx-rs-{}.dict", std::process::id()));
+        std::fs::write(
+            &dictfile,
+            "## custom words\nblorp B L AO R P\nzorptastic Z ZZ QQ\nforward F AO R W ER D\nsnarfle S N AA R F AH L\nwibble W IH B XX\n",
+        )
+        .unwrap();
+        let mut decoder = default_decoder(&[]);
+        let search = decoder.get_current_search().unwrap();
+        let report = decoder
+            .load_dict_collecting(dictfile.to_str().unwrap())
+            .unwrap();
+        std::fs::remove_file(&dictfile).unwrap();
+
+        assert_eq!(report.added, 2);
+        let skipped: Vec<&str> = report.skipped.iter().map(|(w, _)| w.as_str()).collect();
+        assert_eq!(skipped, ["forward", "zorptastic", "wibble"]);
+        assert!(decoder.word_exists("blorp"));
+        assert!(decoder.word_exists("snarfle"));
+        assert!(!decoder.word_exists("wibble"));
+        assert_eq!(decoder.get_current_search().unwrap(), search);
+    }
+
+    #[test]
+    fn active_search_kind_follows_activation() {
+        let mut decoder = default_decoder(&[]);
+        assert_eq!(decoder.active_search_kind(), Some(SearchKind::Ngram));
+
+        decoder.add_keyphrase("wakeup", "go forward").unwrap();
+        decoder.set_activate_search("wakeup").unwrap();
+        assert_eq!(decoder.active_search_kind(), Some(SearchKind::Kws));
+
+        decoder
+            .add_jsgf_string(
+                "command",
+                "#JSGF V1.0; grammar command; public <move> = go (forward | back);",
+            )
+            .unwrap();
+        decoder.set_activate_search("command").unwrap();
+        assert_eq!(decoder.active_search_kind(), Some(SearchKind::Fsg));
+
+        decoder.set_align_text("go forward ten meters").unwrap();
+        assert_eq!(decoder.active_search_kind(), Some(SearchKind::Align));
+    }
+
+    #[test]
+    fn try_n_frames_requires_utterance() {
+        let mut decoder = default_decoder(&[]);
+        assert!(decoder.try_n_frames().is_err());
+
+        decoder.start_utt().unwrap();
+        assert_eq!(decoder.try_n_frames().unwrap(), 0);
+        decoder
+            .process_raw(&goforward_samples(), false, false)
+            .unwrap();
+        decoder.end_utt().unwrap();
+        assert!(decoder.try_n_frames().unwrap() > 0);
+    }
+
+    #[test]
+    fn add_lm_file_reports_missing_file() {
+        let mut decoder = default_decoder(&[]);
+        let error = decoder
+            .add_lm_file("missing", "/nonexistent/model.lm.bin")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("missing"));
+        assert!(error.contains("/nonexistent/model.lm.bin"));
+        assert!(error.contains("error code -1"));
+    }
+
+    #[test]
+    fn word_exists_agrees_with_lookup_word() {
+        let decoder = default_decoder(&[]);
+        for word in ["forward", "meters", "<sil>", "blorptastic", "", "nul\0byte"] {
+            let found = decoder.lookup_word(word).unwrap_or(None).is_some();
+            assert_eq!(decoder.word_exists(word), found, "{}", word);
+        }
+    }
+
+    #[test]
+    fn align_words_restores_search() {
+        let mut decoder = default_decoder(&[]);
+        let search = decoder.get_current_search().unwrap();
+        let words = decoder
+            .align_words(GOFORWARD_TEXT, &goforward_samples())
+            .unwrap();
+        let words: Vec<&str> = words
+            .iter()
+            .map(|segment| strip_alternate_pronunciation(&segment.word))
+            .collect();
+        assert_eq!(words.join(" "), GOFORWARD_TEXT);
+        assert_eq!(decoder.get_current_search().unwrap(), search);
+    }
+
+    #[test]
+    fn set_frame_rate_round_trips() {
+        let mut decoder = default_decoder(&[]);
+        assert_eq!(decoder.get_frame_rate().unwrap(), 100);
+
+        decoder.set_frame_rate(200).unwrap();
+        assert_eq!(decoder.get_frame_rate().unwrap(), 200);
+
+        assert!(decoder.set_frame_rate(0).is_err());
+        assert_eq!(decoder.get_frame_rate().unwrap(), 200);
+    }
+
+    #[test]
+    fn boundary_symbols_are_cached_until_reload() {
+        let mut decoder = default_decoder(&[]);
+        let symbols = decoder.boundary_symbols();
+        assert_eq!(symbols.silence, "<sil>");
+        assert!(!symbols.fillers.is_empty());
+
+        // A changed path is not read until the decoder is reinitialized
+        let mut config = decoder.get_config();
+        let fdict = config.get_str("fdict").unwrap();
+        config.set_str("fdict", "/nonexistent/noisedict").unwrap();
+        assert_eq!(decoder.boundary_symbols(), symbols);
+
+        config.set_str("fdict", &fdict).unwrap();
+        decoder.reload().unwrap();
+        assert_eq!(decoder.boundary_symbols(), symbols);
+    }
+
+    #[test]
+    fn restore_state_reactivates_saved_search() {
+        let mut decoder = default_decoder(&[]);
+        let state = decoder.save_state();
+        let active = state.active_search.clone().unwrap();
+
+        decoder.add_keyphrase("keyphrase", "forward").unwrap();
+        decoder.set_activate_search("keyphrase").unwrap();
+        assert_eq!(decoder.get_current_search().unwrap(), "keyphrase");
+
+        decoder.restore_state(&state).unwrap();
+        assert_eq!(decoder.get_current_search().unwrap(), active);
+        assert!(!decoder.get_search_iter().any(|name| name == "keyphrase"));
+    }
+
+    #[test]
+    fn restart_utterance_starts_fresh() {
+        let mut decoder = default_decoder(&[]);
+        let samples = goforward_samples();
+        decoder.start_utt().unwrap();
+        decoder
+            .process_raw(&samples[..samples.len() / 2], false, false)
+            .unwrap();
+        assert!(decoder.get_n_frames() > 0);
+
+        decoder.restart_utterance().unwrap();
+        assert!(decoder.is_utt_active());
+        assert_eq!(decoder.get_n_frames(), 0);
+
+        decoder.process_raw(&samples, false, true).unwrap();
+        decoder.end_utt().unwrap();
+        let (hyp, _) = decoder.get_hyp().unwrap().unwrap();
+        assert_eq!(hyp, GOFORWARD_TEXT);
+
+        // Without an active utterance it only starts one
+        decoder.restart_utterance().unwrap();
+        assert!(decoder.is_utt_active());
+    }
+
+    #[test]
+    fn utt_id_survives_utterances() {
+        let mut decoder = default_decoder(&[]);
+        assert_eq!(decoder.utt_id(), None);
+
+        decoder.set_utt_id("goforward");
+        decode_utterance(&mut decoder, &goforward_samples()).unwrap();
+        assert_eq!(decoder.utt_id().as_deref(), Some("goforward"));
+
+        decoder.set_utt_id("second");
+        decoder.start_utt().unwrap();
+        decoder.end_utt().unwrap();
+        assert_eq!(decoder.utt_id().as_deref(), Some("second"));
+    }
+
+    #[test]
+    fn decode_raw_file_cancellable_stops_when_cancelled() {
+        let mut decoder = default_decoder(&[]);
+        let cancel = AtomicBool::new(true);
+        let num_samples = decoder
+            .decode_raw_file_cancellable(GOFORWARD_RAW_PATH, None, &cancel)
+            .unwrap();
+        assert_eq!(num_samples, 0);
+        assert!(!decoder.is_utt_active());
+
+        cancel.store(false, Ordering::Relaxed);
+        let num_samples = decoder
+            .decode_raw_file_cancellable(GOFORWARD_RAW_PATH, None, &cancel)
+            .unwrap();
+        assert_eq!(num_samples as usize, goforward_samples().len());
+        let (hyp, _) = decoder.get_hyp().unwrap().unwrap();
+        assert_eq!(hyp, GOFORWARD_TEXT);
+    }
+
+    #[test]
+    fn get_posterior_follows_bestpath() {
+        let samples = goforward_samples();
+        let mut decoder = default_decoder(&[]);
+        assert!(decoder.bestpath_enabled());
+        decode_utterance(&mut decoder, &samples).unwrap();
+        let posterior = decoder.get_posterior().unwrap();
+        assert!(posterior <= 0);
+
+        let mut decoder = default_decoder(&[("bestpath", "no")]);
+        assert!(!decoder.bestpath_enabled());
+        decode_utterance(&mut decoder, &samples).unwrap();
+        assert_eq!(decoder.get_posterior(), None);
+    }
+
+    #[test]
+    fn try_from_config_keeps_configuration() {
+        let mut config = Config::default().unwrap();
+        config.set_str("input_endian", "little").unwrap();
+        let mut decoder = Decoder::try_from(config).unwrap();
+        assert_eq!(
+            decoder.get_config().get_str("input_endian").unwrap(),
+            "little"
+        );
+
+        let (hyp, _) = decode_utterance(&mut decoder, &goforward_samples()).unwrap();
+        assert_eq!(hyp, GOFORWARD_TEXT);
+    }
+
+    #[test]
+    fn latency_frames_counts_unsearched_frames() {
+        let mut decoder = default_decoder(&[]);
+        let samples = goforward_samples();
+        decoder.start_utt().unwrap();
+        assert_eq!(decoder.latency_frames(), 0);
+
+        decoder.process_raw(&samples, true, false).unwrap();
+        let input_frames = (samples.len() / 160) as i32;
+        assert_eq!(decoder.get_n_frames(), 0);
+        assert!((decoder.latency_frames() - input_frames).abs() <= 1);
+
+        decoder.end_utt().unwrap();
+        assert!(decoder.latency_frames() < input_frames);
+    }
+
+    #[test]
+    fn get_hyp_chars_joins_words() {
+        let mut decoder = default_decoder(&[]);
+        decode_utterance(&mut decoder, &goforward_samples()).unwrap();
+        assert_eq!(
+            decoder.get_hyp_chars().unwrap(),
+            GOFORWARD_TEXT.replace(' ', "")
+        );
+    }
+
+    #[test]
+    fn audio_duration_sec_spans_utterances() {
+        let mut decoder = default_decoder(&[]);
+        let samples = goforward_samples();
+        assert_eq!(decoder.audio_duration_sec(), 0.0);
+
+        decode_utterance(&mut decoder, &samples).unwrap();
+        let duration = samples.len() as f64 / 16000.0;
+        assert!((decoder.audio_duration_sec() - duration).abs() < 1e-9);
+
+        decode_utterance(&mut decoder, &samples).unwrap();
+        assert!((decoder.audio_duration_sec() - 2.0 * duration).abs() < 1e-9);
+    }
+
+    #[test]
+    fn process_raw_poll_reports_partial_hypotheses() {
+        let mut decoder = default_decoder(&[]);
+        let samples = goforward_samples();
+        assert!(decoder.process_raw_poll(&samples, 10, |_| {}).is_err());
+
+        decoder.start_utt().unwrap();
+        assert!(decoder.process_raw_poll(&samples, 0, |_| {}).is_err());
+        let mut partials = Vec::new();
+        decoder
+            .process_raw_poll(&samples, 10, |hyp| partials.push(hyp.to_string()))
+            .unwrap();
+        decoder.end_utt().unwrap();
+
+        assert_eq!(partials.len(), samples.len() / 1600);
+        assert!(partials.last().unwrap().contains("forward"));
+    }
+
+    #[test]
+    fn align_nbest_aligns_each_hypothesis() {
+        let mut decoder = default_decoder(&[]);
+        let samples = goforward_samples();
+        decode_utterance(&mut decoder, &samples).unwrap();
+        let search = decoder.get_current_search().unwrap();
+
+        let alignments = decoder.align_nbest(3, &samples).unwrap();
+        assert!(!alignments.is_empty() && alignments.len() <= 3);
+        assert_eq!(alignments[0].0, GOFORWARD_TEXT);
+        assert_eq!(decoder.get_current_search().unwrap(), search);
+
+        // Earlier alignments stay valid after the following passes
+        let symbols = decoder.boundary_symbols();
+        for (hyp, alignment) in &alignments {
+            let words: Vec<String> = alignment
+                .get_words()
+                .map(|word| strip_alternate_pronunciation(word.get_name()).to_string())
+                .filter(|word| !symbols.is_filler(word))
+                .collect();
+            assert_eq!(&words.join(" "), hyp);
+        }
+    }
+
+    #[test]
+    fn search_vocabulary_lists_search_words() {
+        let mut decoder = default_decoder(&[]);
+        decoder
+            .add_jsgf_string(
+                "commands",
+                "#JSGF V1.0;\ngrammar commands;\npublic <command> = go (forward | back);\n",
+            )
+            .unwrap();
+        assert_eq!(
+            decoder.search_vocabulary(Some("commands")).unwrap(),
+            ["back", "forward", "go"]
+        );
+
+        let vocabulary = decoder.search_vocabulary(None).unwrap();
+        assert!(vocabulary.iter().any(|word| word == "meters"));
+        assert!(!vocabulary.iter().any(|word| word.starts_with('<')));
+
+        decoder.add_keyphrase("keyphrase", "forward").unwrap();
+        assert!(decoder
+            .search_vocabulary(Some("keyphrase"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn score_breakdown_sums_segment_scores() {
+        let mut decoder = default_decoder(&[]);
+        let (_, score) = decode_utterance(&mut decoder, &goforward_samples()).unwrap();
+        let breakdown = decoder.score_breakdown().unwrap();
+        assert_eq!(breakdown.total, score);
+        assert!(breakdown.acoustic < 0);
+        assert!(breakdown.lm < 0);
+
+        let acoustic: i32 = decoder
+            .get_seg_iter()
+            .unwrap()
+            .map(|seg| seg.get_prob().am_score)
+            .sum();
+        assert_eq!(breakdown.acoustic, acoustic);
+    }
+
+    #[test]
+    fn pronunciations_lists_variants() {
+        let decoder = default_decoder(&[]);
+        let pronunciations = decoder.pronunciations("read").unwrap();
+        assert!(pronunciations.len() >= 2);
+        assert_eq!(
+            pronunciations[0],
+            decoder.lookup_word("read").unwrap().unwrap()
+        );
+        assert_eq!(
+            pronunciations[1],
+            decoder.lookup_word("read(2)").unwrap().unwrap()
+        );
+
+        assert!(!decoder.pronunciations("forward").unwrap().is_empty());
+        assert!(decoder.pronunciations("xyzzyplugh").unwrap().is_empty());
+    }
+
+    #[test]
+    fn raw_round_trip_keeps_decoder_usable() {
+        let decoder = default_decoder(&[]);
+        let mut decoder = unsafe { Decoder::from_raw(decoder.into_raw()) };
+        let (hyp, _) = decode_utterance(&mut decoder, &goforward_samples()).unwrap();
+        assert_eq!(hyp, GOFORWARD_TEXT);
+
+        // A retained handle hands out a reference of its own
+        let retained = unsafe { decoder.retain() };
+        drop(unsafe { Decoder::from_raw(retained.into_raw()) });
+        let (hyp, _) = decode_utterance(&mut decoder, &goforward_samples()).unwrap();
+        assert_eq!(hyp, GOFORWARD_TEXT);
+    }
+
+    #[test]
+    fn decode_path_detects_format() {
+        let samples = goforward_samples();
+        let mut decoder = default_decoder(&[("input_endian", "little")]);
+
+        let wav = temp_path("decode-path.wav");
+        crate::audio::write_wav(&wav, &samples, 16000).unwrap();
+        let (hyp, _) = decoder.decode_path(&wav).unwrap().unwrap();
+        assert_eq!(hyp, GOFORWARD_TEXT);
+
+        let sph = temp_path("decode-path.sph");
+        let mut header = String::from("NIST_1A\n   1024\n");
+        header.push_str("sample_rate -i 16000\nchannel_count -i 1\nsample_n_bytes -i 2\n");
+        header.push_str("sample_byte_format -s2 10\nsample_coding -s3 pcm\nend_head\n");
+        let mut bytes = header.into_bytes();
+        bytes.resize(1024, b' ');
+        bytes.extend(samples.iter().flat_map(|s| s.to_be_bytes()));
+        std::fs::write(&sph, bytes).unwrap();
+        let (hyp, _) = decoder.decode_path(&sph).unwrap().unwrap();
+        assert_eq!(hyp, GOFORWARD_TEXT);
+
+        let (hyp, _) = decoder.decode_path(GOFORWARD_RAW_PATH).unwrap().unwrap();
+        assert_eq!(hyp, GOFORWARD_TEXT);
+
+        // A corrupt file with a known extension is not decoded as raw audio
+        std::fs::write(&wav, goforward_raw()).unwrap();
+        assert!(decoder.decode_path(&wav).is_err());
+
+        std::fs::remove_file(&wav).unwrap();
+        std::fs::remove_file(&sph).unwrap();
+    }
+
+    #[test]
+    fn active_search_object_follows_search() {
+        let mut decoder = default_decoder(&[]);
+        assert!(matches!(
+            decoder.active_search_object(),
+            ActiveSearch::Ngram(_)
+        ));
+
+        decoder
+            .add_jsgf_string(
+                "commands",
+                "#JSGF V1.0;\ngrammar commands;\npublic <command> = go forward;\n",
+            )
+            .unwrap();
+        decoder.set_activate_search("commands").unwrap();
+        assert!(matches!(
+            decoder.active_search_object(),
+            ActiveSearch::Fsg(_)
+        ));
+
+        decoder.add_keyphrase("keyphrase", "go forward").unwrap();
+        decoder.set_activate_search("keyphrase").unwrap();
+        match decoder.active_search_object() {
+            ActiveSearch::Kws(keyphrase) => assert_eq!(keyphrase, "go forward"),
+            _ => panic!("expected a keyphrase search"),
+        }
+    }
+
+    #[test]
+    fn model_topology_of_default_model() {
+        let decoder = default_decoder(&[]);
+        let n_senones = decoder.n_senones().unwrap();
+        let n_mixtures = decoder.n_mixtures().unwrap();
+        assert!(n_senones > 0);
+        assert!(n_mixtures > 0);
+
+        let hmm = decoder.get_config().get_str("hmm").unwrap();
+        decoder
+            .get_config()
+            .set_str("mdef", &format!("{}/mdef", hmm))
+            .unwrap();
+        assert_eq!(decoder.n_senones().unwrap(), n_senones);
+        decoder
+            .get_config()
+            .set_str("mdef", &format!("{}/missing", hmm))
+            .unwrap();
+        assert!(decoder.n_senones().is_err());
+    }
+
+    #[test]
+    fn decode_full_collects_all_results() {
+        let mut decoder = default_decoder(&[]);
+        let full = decoder.decode_full(&goforward_samples(), 5).unwrap();
+
+        let (best, score) = full.best.clone().unwrap();
+        assert_eq!(best, GOFORWARD_TEXT);
+        assert_eq!(decoder.get_hyp().unwrap(), Some((best.clone(), score)));
+
+        let symbols = decoder.boundary_symbols();
+        let words: Vec<&str> = full
+            .segments
+            .iter()
+            .map(|segment| strip_alternate_pronunciation(&segment.word))
+            .filter(|word| !symbols.is_filler(word))
+            .collect();
+        assert_eq!(words.join(" "), best);
+
+        assert!(!full.nbest.is_empty() && full.nbest.len() <= 5);
+        assert_eq!(full.nbest[0].hypothesis, best);
+    }
+
+    #[test]
+    fn silence_has_no_hypothesis() {
+        let mut decoder = default_decoder(&[]);
+        assert_eq!(decode_utterance(&mut decoder, &[0; 16000]), None);
+        assert!(decoder.get_alignment().is_none());
+    }
+
+    #[test]
+    fn load_feat_params_keeps_searches() {
+        let mut decoder = default_decoder(&[]);
+        decoder.add_keyphrase("keyphrase", "forward").unwrap();
+
+        let path = temp_path("feat.params");
+        std::fs::write(
+            &path,
+            "-upperf 6000\n# comment\n\n-not_a_parameter 1\n-nfilt 30\n",
+        )
+        .unwrap();
+        decoder.load_feat_params(&path).unwrap();
+        let config = decoder.get_config();
+        assert_eq!(config.get_float("upperf").unwrap(), 6000.0);
+        assert_eq!(config.get_int("nfilt").unwrap(), 30);
+        assert!(decoder.get_search_iter().any(|name| name == "keyphrase"));
+
+        std::fs::write(&path, "-upperf\n").unwrap();
+        assert!(decoder.load_feat_params(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn get_kws_with_name() {
+        let mut decoder = default_decoder(&[]);
+        decoder.add_keyphrase("keyphrase", "go forward").unwrap();
+        assert_eq!(
+            decoder.get_kws(Some("keyphrase")).unwrap().as_deref(),
+            Some("go forward")
+        );
+        let current = decoder.get_current_search().unwrap();
+        assert_eq!(decoder.get_kws(Some(&current)).unwrap(), None);
+    }
+
+    #[test]
+    fn nbest_with_posteriors_normalizes() {
+        let mut decoder = default_decoder(&[]);
+        decode_utterance(&mut decoder, &goforward_samples()).unwrap();
+        let results = decoder.nbest_with_posteriors(5);
+
+        assert!(!results.is_empty() && results.len() <= 5);
+        assert_eq!(results[0].hypothesis, GOFORWARD_TEXT);
+        let total: f64 = results.iter().map(|result| result.posterior).sum();
+        assert!((total - 1.0).abs() < 0.01, "posteriors sum up to {}", total);
+        for pair in results.windows(2) {
+            assert!(pair[0].posterior >= pair[1].posterior);
+        }
+    }
+
+    #[test]
+    fn feed_continuous_segments_stream() {
+        let mut decoder = default_decoder(&[]);
+        let endpointer = Endpointer::default().unwrap();
+        let speech = goforward_samples();
+        let mut samples = vec![0; 16000];
+        samples.extend(&speech);
+        samples.extend(vec![0; 16000]);
+        samples.extend(&speech);
+        samples.extend(vec![0; 16000]);
+
+        // Chunks that do not line up with the endpointer frames
+        let mut results = Vec::new();
+        for chunk in samples.chunks(1000) {
+            results.extend(decoder.feed_continuous(&endpointer, chunk).unwrap());
+        }
+        assert!(results.len() >= 2);
+        assert!(results.iter().any(|(text, _, _)| text.contains("forward")));
+        for (_, start, end) in &results {
+            assert!(start < end);
+        }
+        assert!(!decoder.is_utt_active());
+    }
+
+    #[test]
+    fn lookup_word_returns_owned_pronunciation() {
+        let mut decoder = default_decoder(&[]);
+        decoder
+            .add_word("forwardly", "F AO R W ER D L IY", true)
+            .unwrap();
+        for _ in 0..1000 {
+            assert_eq!(
+                decoder.lookup_word("forwardly").unwrap().as_deref(),
+                Some("F AO R W ER D L IY")
+            );
+        }
+        assert_eq!(decoder.lookup_word("xyzzyplugh").unwrap(), None);
+    }
+
+    #[test]
+    fn align_words_with_custom_tokenizer() {
+        struct CommaTokenizer;
+
+        impl crate::Tokenizer for CommaTokenizer {
+            fn tokenize(&self, text: &str) -> Vec<String> {
+                text.split(',').map(|word| word.to_string()).collect()
+            }
+        }
+
+        let mut decoder = default_decoder(&[]);
+        let samples = goforward_samples();
+        let expected = decoder.align_words(GOFORWARD_TEXT, &samples).unwrap();
+        let words = decoder
+            .align_words_with(&GOFORWARD_TEXT.replace(' ', ","), &samples, &CommaTokenizer)
+            .unwrap();
+        assert_eq!(words, expected);
+    }
+
+    #[test]
+    fn process_with_vad_starts_utterance_at_speech() {
+        let mut decoder = default_decoder(&[]);
+        let mut vad = VAD::new(VADMode::Loose, Some(16000), None).unwrap();
+        let frame_size = vad.get_frame_size();
+
+        assert!(decoder
+            .process_with_vad(&mut vad, &vec![0; frame_size + 1])
+            .is_err());
+        assert_eq!(
+            decoder
+                .process_with_vad(&mut vad, &vec![0; frame_size])
+                .unwrap(),
+            (false, None)
+        );
+        assert!(!decoder.is_utt_active());
+
+        let mut heard_speech = false;
+        let mut partial = None;
+        for frame in goforward_samples().chunks_exact(frame_size) {
+            let (is_speech, hyp) = decoder.process_with_vad(&mut vad, frame).unwrap();
+            heard_speech |= is_speech;
+            partial = hyp.or(partial);
+        }
+        assert!(heard_speech);
+        assert!(decoder.is_utt_active());
+        assert!(partial.unwrap().contains("forward"));
+        decoder.end_utt().unwrap();
+    }
+
+    #[test]
+    fn get_lm_outlives_decoder() {
+        let decoder = default_decoder(&[]);
+        let lm = decoder.get_lm(None).unwrap();
+        let counts = lm.get_counts();
+        let logprob = lm.sentence_logprob(GOFORWARD_TEXT);
+        drop(decoder);
+
+        assert_eq!(lm.get_counts(), counts);
+        assert_eq!(lm.sentence_logprob(GOFORWARD_TEXT), logprob);
+        assert!(lm.wid("forward") != lm.unknown_wid());
+    }
+
+    #[test]
+    fn add_lm_shares_model() {
+        let lm = default_decoder(&[]).get_lm(None).unwrap();
+        let mut decoder = default_decoder(&[]);
+        decoder.add_lm("shared", &lm).unwrap();
+        let logprob = lm.sentence_logprob(GOFORWARD_TEXT);
+
+        decoder.set_activate_search("shared").unwrap();
+        let (hyp, _score) = decode_utterance(&mut decoder, &goforward_samples()).unwrap();
+        assert_eq!(hyp, GOFORWARD_TEXT);
+        assert_eq!(lm.sentence_logprob(GOFORWARD_TEXT), logprob);
+
+        drop(lm);
+        let (hyp, _score) = decode_utterance(&mut decoder, &goforward_samples()).unwrap();
+        assert_eq!(hyp, GOFORWARD_TEXT);
+    }
+}
@@ -1,10 +1,19 @@
+use std::cell::Cell;
 use std::error::Error;
 
+use crate::decoder::Decoder;
 use crate::vad::{VADMode, VAD};
 
+/// A decoded speech segment as (start, end, text) with start and end time in seconds.
+pub type DecodedSegment = (f64, f64, String);
+
 pub struct Endpointer {
     inner: *mut pocketsphinx_sys::ps_endpointer_t,
     retained: bool,
+    /// Number of frames passed to `Endpointer::process()` since the last reset.
+    processed_frames: Cell<usize>,
+    /// Number of those frames that were returned as speech.
+    speech_frames: Cell<usize>,
 }
 
 impl Endpointer {
@@ -15,9 +24,9 @@ impl Endpointer {
     /// - `ratio`          - Ratio of frames needed to trigger start/end decision, or `None` for the default (`Endpointer::get_default_ratio()`).
     /// - `mode`           - "Aggressiveness" of voice activity detection. Stricter values (see `VADMode`) are less likely to misclassify non-speech as speech.
     /// - `sample_rate`    - Sampling rate of input, or `None` for default (which can be obtained with `VAD::get_sample_rate()`). Only `8000`, `16000`, `32000`, `48000` are directly supported, others will use the closest supported rate (within reason).
-    ///                      Note that this means that the actual frame length may not be exactly the one requested, so you must always use the one returned by `Endpointer::get_frame_size()` (in samples) or `Endpointer::get_frame_length()` (in seconds).
+    ///   Note that this means that the actual frame length may not be exactly the one requested, so you must always use the one returned by `Endpointer::get_frame_size()` (in samples) or `Endpointer::get_frame_length_seconds()` (in seconds).
     /// - `frame_length`   - Requested frame length in seconds, or `None` for the default. Only `0.01`, `0.02`, `0.03` currently supported.
-    ///                      **Actual frame length may be different, you must always use `Endpointer::get_frame_length()` to obtain it.**
+    ///   **Actual frame length may be different, you must always use `Endpointer::get_frame_length_seconds()` to obtain it.**
     pub fn new(
         window: Option<f64>,
         ratio: Option<f64>,
@@ -44,11 +53,14 @@ impl Endpointer {
             Ok(Self {
                 inner,
                 retained: false,
+                processed_frames: Cell::new(0),
+                speech_frames: Cell::new(0),
             })
         }
     }
 
     /// Initialize endpointing with default parameters.
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Result<Self, Box<dyn Error>> {
         Self::new(None, None, VADMode::Loose, None, None)
     }
@@ -57,12 +69,13 @@ impl Endpointer {
     ///
     /// # Returns
     /// Endpointer with incremented reference count.
-    pub fn retain(&mut self) -> Self {
+    pub fn retain(&self) -> Self {
         let retained_inner = unsafe { pocketsphinx_sys::ps_endpointer_retain(self.inner) };
-        self.retained = true;
         Self {
             inner: retained_inner,
             retained: false,
+            processed_frames: Cell::new(0),
+            speech_frames: Cell::new(0),
         }
     }
 
@@ -83,16 +96,64 @@ impl Endpointer {
     ///
     /// # Returns
     /// `None` if no speech available, or a slice of a frame of `Endpointer::frame_size()` samples (no more and no less).
+    ///
+    /// # Panics
+    /// If `frame` does not contain exactly `Endpointer::get_frame_size()` samples, since pocketsphinx always reads a full frame.
     pub fn process(&self, frame: &[i16]) -> Option<&[i16]> {
+        let frame_size = self.get_frame_size();
+        assert_eq!(
+            frame.len(),
+            frame_size,
+            "expected {} samples, got {}",
+            frame_size,
+            frame.len()
+        );
         let result = unsafe { pocketsphinx_sys::ps_endpointer_process(self.inner, frame.as_ptr()) };
+        self.processed_frames.set(self.processed_frames.get() + 1);
         if result.is_null() {
             None
         } else {
-            let frame = unsafe { std::slice::from_raw_parts(result, self.get_frame_size()) };
+            self.speech_frames.set(self.speech_frames.get() + 1);
+            let frame = unsafe { std::slice::from_raw_parts(result, frame_size) };
             Some(frame)
         }
     }
 
+    /// Get the fraction of frames that were in a speech region.
+    ///
+    /// Counts the frames passed to `Endpointer::process()` since the endpointer was created or `Endpointer::reset_speech_ratio()` was called.
+    /// Since speech frames are returned with a delay, the ratio is only meaningful over a stream that is long compared to the endpointer window.
+    ///
+    /// # Returns
+    /// Ratio between `0.0` and `1.0`, or `0.0` if no frames were processed.
+    pub fn speech_ratio(&self) -> f64 {
+        let processed_frames = self.processed_frames.get();
+        if processed_frames == 0 {
+            0.0
+        } else {
+            self.speech_frames.get() as f64 / processed_frames as f64
+        }
+    }
+
+    /// Reset the frame counts used by `Endpointer::speech_ratio()`.
+    pub fn reset_speech_ratio(&self) {
+        self.processed_frames.set(0);
+        self.speech_frames.set(0);
+    }
+
+    /// Process a frame of audio, returning an owned copy of the frame if in a speech region.
+    ///
+    /// Unlike `Endpointer::process()`, the returned frame stays valid after the next call, so it can be stored (e.g. to reassemble an utterance).
+    ///
+    /// # Arguments
+    /// - `frame` - Frame of audio. Must be the same length as the frame length specified when the endpointer was created.
+    ///
+    /// # Returns
+    /// `None` if no speech available, or a frame of `Endpointer::get_frame_size()` samples.
+    pub fn process_owned(&self, frame: &[i16]) -> Option<Vec<i16>> {
+        self.process(frame).map(|speech| speech.to_vec())
+    }
+
     /// Process remaining samples at end of stream.
     ///
     /// Note that the endpointer is not thread-safe. You must call all endpointer functions from the same thread.
@@ -101,7 +162,7 @@ impl Endpointer {
     /// - `frame` - Frame of data, must contain `Endpointer::get_frame_size()` samples or less.
     ///
     /// # Returns
-    /// Slice to available samples, or `None` if none available.
+    /// Slice to available samples, or `None` if none available. The slice contains the number of samples reported by pocketsphinx, which may be shorter than a full frame.
     pub fn end_stream(&self, frame: &[i16]) -> Option<&[i16]> {
         let mut out_nsamp = 0;
         let result = unsafe {
@@ -115,12 +176,65 @@ impl Endpointer {
         if result.is_null() {
             None
         } else {
-            let available_samples =
-                unsafe { std::slice::from_raw_parts(result, self.get_frame_size()) };
+            let available_samples = unsafe { std::slice::from_raw_parts(result, out_nsamp) };
             Some(available_samples)
         }
     }
 
+    /// Endpoint a complete audio buffer and decode every detected speech segment.
+    ///
+    /// Every segment is decoded as a separate utterance. The stream is ended after the buffer, so this is meant for offline audio.
+    ///
+    /// # Arguments
+    /// - `decoder` - Decoder used to decode the speech segments. It must not have an active utterance.
+    /// - `audio`   - Audio with the sample rate of the endpointer.
+    ///
+    /// # Returns
+    /// A vector of (start, end, text) tuples with the start and end time of each segment in seconds. Segments without a hypothesis are skipped.
+    pub fn decode_stream(
+        &self,
+        decoder: &mut Decoder,
+        audio: &[i16],
+    ) -> Result<Vec<DecodedSegment>, Box<dyn Error>> {
+        let mut segments = Vec::new();
+        let frames = audio.chunks_exact(self.get_frame_size());
+        let remainder = frames.remainder();
+        for frame in frames {
+            if let Some(speech) = self.process(frame) {
+                if !decoder.is_utt_active() {
+                    decoder.start_utt()?;
+                }
+                decoder.process_raw(speech, false, false)?;
+                if !self.get_in_speech() {
+                    decoder.end_utt()?;
+                    self.push_segment(decoder, &mut segments)?;
+                }
+            }
+        }
+        if let Some(speech) = self.end_stream(remainder) {
+            if !decoder.is_utt_active() {
+                decoder.start_utt()?;
+            }
+            decoder.process_raw(speech, false, false)?;
+        }
+        if decoder.is_utt_active() {
+            decoder.end_utt()?;
+            self.push_segment(decoder, &mut segments)?;
+        }
+        Ok(segments)
+    }
+
+    fn push_segment(
+        &self,
+        decoder: &Decoder,
+        segments: &mut Vec<DecodedSegment>,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some((text, _score)) = decoder.get_hyp()? {
+            segments.push((self.get_speech_start(), self.get_speech_end(), text));
+        }
+        Ok(())
+    }
+
     /// Get the current state (speech/not-speech) of the endpointer.
     ///
     /// This function can be used to detect speech/non-speech transitions.
@@ -143,6 +257,21 @@ impl Endpointer {
         unsafe { pocketsphinx_sys::ps_endpointer_speech_end(self.inner) }
     }
 
+    /// Get the start of the last speech segment as a sample index of the input stream, e.g. to slice the original audio buffer.
+    pub fn get_speech_start_sample(&self) -> i64 {
+        self.seconds_to_sample(self.get_speech_start())
+    }
+
+    /// Get the end of the last speech segment as a sample index of the input stream.
+    pub fn get_speech_end_sample(&self) -> i64 {
+        self.seconds_to_sample(self.get_speech_end())
+    }
+
+    /// Convert a time in seconds to the index of the nearest sample.
+    fn seconds_to_sample(&self, seconds: f64) -> i64 {
+        (seconds * self.get_sample_rate() as f64).round() as i64
+    }
+
     /// Default window in seconds of audio to use for speech start/end decision.
     ///
     /// @see https://cmusphinx.github.io/doc/pocketsphinx/endpointer_8h.html#a66481b47838efb4704b9483893cd1c8a
@@ -164,13 +293,20 @@ impl Endpointer {
         self.get_vad().get_frame_size()
     }
 
-    /// Get the frame length required by the endpointer.
+    /// Get the frame length required by the endpointer in milliseconds (rounded down).
     ///
-    /// @see https://cmusphinx.github.io/doc/pocketsphinx/endpointer_8h.html#a65e580fb57829e172863093b8d8bfdf7
+    /// **Unit change:** earlier versions documented this as seconds, but always returned `0`. Use `Endpointer::get_frame_length_seconds()` for seconds.
     pub fn get_frame_length(&self) -> i32 {
         self.get_vad().get_frame_length()
     }
 
+    /// Get the frame length required by the endpointer in seconds.
+    ///
+    /// @see https://cmusphinx.github.io/doc/pocketsphinx/endpointer_8h.html#a65e580fb57829e172863093b8d8bfdf7
+    pub fn get_frame_length_seconds(&self) -> f64 {
+        self.get_vad().get_frame_length_seconds()
+    }
+
     /// Get the sample rate required by the endpointer.
     ///
     /// @see https://cmusphinx.github.io/doc/pocketsphinx/endpointer_8h.html#a1b52b6d6bf58004f463ad01697b1076f
@@ -190,3 +326,140 @@ impl Drop for Endpointer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{default_decoder, goforward_samples};
+
+    #[test]
+    fn default_frame_length() {
+        let endpointer = Endpointer::new(None, None, VADMode::Loose, None, None).unwrap();
+        assert!((endpointer.get_frame_length_seconds() - 0.03).abs() < 1e-3);
+        assert_eq!(endpointer.get_frame_length(), 30);
+        assert_eq!(
+            endpointer.get_frame_size(),
+            endpointer.get_sample_rate() as usize * 30 / 1000
+        );
+    }
+    /// Two recordings of `goforward.raw`, each surrounded by a second of silence.
+    fn two_utterances() -> Vec<i16> {
+        let speech = goforward_samples();
+        let mut samples = vec![0; 16000];
+        samples.extend(&speech);
+        samples.extend(vec![0; 16000]);
+        samples.extend(&speech);
+        samples.extend(vec![0; 16000]);
+        samples
+    }
+
+    #[test]
+    fn decode_stream_splits_utterances() {
+        let endpointer = Endpointer::default().unwrap();
+        let mut decoder = default_decoder(&[]);
+        let segments = endpointer
+            .decode_stream(&mut decoder, &two_utterances())
+            .unwrap();
+
+        assert!(!decoder.is_utt_active());
+        assert!(segments.len() >= 2);
+        assert!(segments.iter().any(|(_, _, text)| text.contains("forward")));
+        for (start, end, _) in &segments {
+            assert!(start < end);
+        }
+        for pair in segments.windows(2) {
+            assert!(pair[0].1 <= pair[1].0);
+        }
+    }
+
+    #[test]
+    fn process_owned_matches_process() {
+        let samples = two_utterances();
+        let borrowing = Endpointer::default().unwrap();
+        let owning = Endpointer::default().unwrap();
+        let frame_size = owning.get_frame_size();
+
+        let mut owned_frames = Vec::new();
+        for frame in samples.chunks_exact(frame_size) {
+            let expected = borrowing.process(frame).map(|speech| speech.to_vec());
+            let owned = owning.process_owned(frame);
+            assert_eq!(owned, expected);
+            owned_frames.extend(owned);
+        }
+        // The stored frames are still intact after further processing
+        assert!(!owned_frames.is_empty());
+        assert!(owned_frames.iter().all(|frame| frame.len() == frame_size));
+        assert!(owned_frames
+            .iter()
+            .any(|frame| frame.iter().any(|&s| s != 0)));
+    }
+
+    #[test]
+    fn speech_ratio_over_stream() {
+        let endpointer = Endpointer::default().unwrap();
+        let frame_size = endpointer.get_frame_size();
+        assert_eq!(endpointer.speech_ratio(), 0.0);
+
+        for frame in vec![0; 16000].chunks_exact(frame_size) {
+            endpointer.process(frame);
+        }
+        assert_eq!(endpointer.speech_ratio(), 0.0);
+
+        for frame in two_utterances().chunks_exact(frame_size) {
+            endpointer.process(frame);
+        }
+        let ratio = endpointer.speech_ratio();
+        assert!(ratio > 0.0 && ratio < 1.0, "ratio {}", ratio);
+
+        endpointer.reset_speech_ratio();
+        assert_eq!(endpointer.speech_ratio(), 0.0);
+    }
+
+    #[test]
+    fn end_stream_returns_remaining_samples() {
+        let endpointer = Endpointer::default().unwrap();
+        let frame_size = endpointer.get_frame_size();
+        let mut samples = vec![0; 16000];
+        samples.extend(goforward_samples());
+        // Stop in the middle of the speech, leaving a partial frame
+        let n_frames = (16000 + goforward_samples().len() / 2) / frame_size;
+        for frame in samples[..n_frames * frame_size].chunks_exact(frame_size) {
+            endpointer.process(frame);
+        }
+        assert!(endpointer.get_in_speech());
+
+        let remainder = &samples[n_frames * frame_size..n_frames * frame_size + 100];
+        let speech = endpointer.end_stream(remainder).unwrap();
+        assert_eq!(speech.len() % frame_size, remainder.len());
+        assert_eq!(&speech[speech.len() - remainder.len()..], remainder);
+    }
+
+    #[test]
+    fn speech_timestamps_in_samples() {
+        let endpointer = Endpointer::default().unwrap();
+        let frame_size = endpointer.get_frame_size() as i64;
+        let sample_rate = endpointer.get_sample_rate() as f64;
+        let samples = two_utterances();
+        let mut ended = false;
+        for frame in samples.chunks_exact(frame_size as usize) {
+            if endpointer.process(frame).is_some() && !endpointer.get_in_speech() {
+                ended = true;
+                break;
+            }
+        }
+        assert!(ended);
+
+        let start = endpointer.get_speech_start_sample();
+        let end = endpointer.get_speech_end_sample();
+        assert_eq!(
+            start,
+            (endpointer.get_speech_start() * sample_rate).round() as i64
+        );
+        assert_eq!(
+            end,
+            (endpointer.get_speech_end() * sample_rate).round() as i64
+        );
+        assert_eq!(start % frame_size, 0);
+        assert!(0 < start && start < end && end <= samples.len() as i64);
+    }
+}
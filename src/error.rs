@@ -0,0 +1,73 @@
+use std::ffi::CString;
+
+/// Errors with a specific cause that callers may want to handle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PocketSphinxError {
+    /// A string argument contains a nul byte and can not be passed to pocketsphinx.
+    NulByte {
+        /// Name of the argument containing the nul byte.
+        arg: &'static str,
+        /// Byte position of the first nul byte in the argument.
+        pos: usize,
+    },
+}
+
+impl std::fmt::Display for PocketSphinxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PocketSphinxError::NulByte { arg, pos } => {
+                write!(
+                    f,
+                    "Argument `{}` contains a nul byte at position {}",
+                    arg, pos
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PocketSphinxError {}
+
+/// Convert a string argument to a `CString`, reporting which argument contained a nul byte on failure.
+pub(crate) fn to_cstring(arg: &'static str, value: &str) -> Result<CString, PocketSphinxError> {
+    CString::new(value).map_err(|e| PocketSphinxError::NulByte {
+        arg,
+        pos: e.nul_position(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::default_decoder;
+
+    #[test]
+    fn to_cstring_reports_nul_position() {
+        assert_eq!(to_cstring("name", "plain").unwrap().as_bytes(), b"plain");
+        assert_eq!(
+            to_cstring("name", "bad\0name").unwrap_err(),
+            PocketSphinxError::NulByte {
+                arg: "name",
+                pos: 3
+            }
+        );
+    }
+
+    #[test]
+    fn decoder_reports_nul_byte_as_typed_error() {
+        let mut decoder = default_decoder(&[]);
+        let error = decoder.set_activate_search("bad\0name").unwrap_err();
+        let error = error.downcast_ref::<PocketSphinxError>().unwrap();
+        assert_eq!(
+            *error,
+            PocketSphinxError::NulByte {
+                arg: "name",
+                pos: 3
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "Argument `name` contains a nul byte at position 3"
+        );
+    }
+}
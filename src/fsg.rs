@@ -56,22 +56,20 @@ impl FSG {
     /// - `decoder` - Decoder object.
     /// - `name` - Name of FSG search, or `None` for current search
     pub fn from_decoder(decoder: &Decoder, name: Option<&str>) -> Option<Self> {
-        let c_name = match name {
-            Some(name) => Some(CString::new(name).unwrap()),
-            None => None,
-        };
-        let c_name_ptr = match c_name {
-            Some(c_name) => c_name.as_ptr(),
-            None => std::ptr::null(),
-        };
+        // A name containing a nul byte can not name a search
+        let c_name = name.map(CString::new).transpose().ok()?;
+        // Borrow the CString, so it stays alive until after the FFI call
+        let c_name_ptr = c_name
+            .as_ref()
+            .map_or(std::ptr::null(), |c_name| c_name.as_ptr());
         let inner = unsafe { pocketsphinx_sys::ps_get_fsg(decoder.get_inner(), c_name_ptr) };
         if inner.is_null() {
             None
         } else {
-            // TODO: Check if the pointer is freed when the decoder is dropped (then it should be retained).
+            // The decoder owns the FSG, use FSG::retain() to keep it beyond the lifetime of the search
             Some(Self {
                 inner,
-                retained: false,
+                retained: true,
             })
         }
     }
@@ -80,9 +78,8 @@ impl FSG {
     ///
     /// # Returns
     /// A new FSG with the same underlying pointer.
-    pub fn retain(&mut self) -> Self {
+    pub fn retain(&self) -> Self {
         let retained_inner = unsafe { pocketsphinx_sys::fsg_model_retain(self.inner) };
-        self.retained = true;
         Self {
             inner: retained_inner,
             retained: false,
@@ -189,3 +186,26 @@ impl Drop for FSG {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::default_decoder;
+
+    #[test]
+    fn from_decoder_with_name() {
+        let mut decoder = default_decoder(&[]);
+        let current = decoder.get_current_search().unwrap();
+        decoder
+            .add_jsgf_string(
+                "commands",
+                "#JSGF V1.0;\ngrammar commands;\npublic <command> = go (forward | back);\n",
+            )
+            .unwrap();
+
+        let fsg = decoder.get_fsg(Some("commands")).unwrap();
+        assert!(fsg.accept("go back"));
+        assert!(!fsg.accept("go ten meters"));
+        assert!(decoder.get_fsg(Some(&current)).is_none());
+        assert!(decoder.get_fsg(Some("missing")).is_none());
+    }
+}
@@ -1,6 +1,7 @@
 use std::error::Error;
 
 use crate::{
+    error::to_cstring,
     fsg::FSG,
     jsgf_rule_iter::{JSGFRule, JSGFRuleIter},
     logmath::LogMath,
@@ -17,7 +18,7 @@ impl JSGF {
     /// - `path` - Path to the file to parse.
     /// - `parent` - Optional parent grammar (`None`, usually).
     pub fn from_file(path: &str, parent: Option<&JSGF>) -> Result<Self, Box<dyn Error>> {
-        let c_path = std::ffi::CString::new(path)?;
+        let c_path = to_cstring("path", path)?;
         let parent = parent.map(|p| p.inner).unwrap_or(std::ptr::null_mut());
         let inner = unsafe { pocketsphinx_sys::jsgf_parse_file(c_path.as_ptr(), parent) };
         if inner.is_null() {
@@ -33,11 +34,11 @@ impl JSGF {
     /// - `string` - JSGF string to parse.
     /// - `parent` - Optional parent grammar (`None`, usually).
     pub fn from_string(string: &str, parent: Option<&JSGF>) -> Result<Self, Box<dyn Error>> {
-        let c_string = std::ffi::CString::new(string)?;
+        let c_string = to_cstring("string", string)?;
         let parent = parent.map(|p| p.inner).unwrap_or(std::ptr::null_mut());
         let inner = unsafe { pocketsphinx_sys::jsgf_parse_string(c_string.as_ptr(), parent) };
         if inner.is_null() {
-            Err("Failed to parse JSGF grammar from string".into())
+            Err(Box::new(JsgfParseError::locate(string)))
         } else {
             Ok(Self { inner })
         }
@@ -85,12 +86,40 @@ impl JSGF {
         FSG::from_jsgf(self, rule, logmath, lw)
     }
 
+    /// Build a Sphinx FSG object from a JSGF rule given by name.
+    ///
+    /// # Arguments
+    /// - `rule_name` - Name of the rule, with or without surrounding angle brackets. Names without a grammar (`rule` instead of `grammar.rule`) refer to a rule of this grammar.
+    /// - `logmath`   - Log-math object used for the transition probabilities.
+    /// - `lw`        - Language weight applied to the transition probabilities.
+    ///
+    /// # Returns
+    /// The FSG or an error if the grammar has no rule with that name.
+    pub fn build_fsg_named(
+        &self,
+        rule_name: &str,
+        logmath: &LogMath,
+        lw: f32,
+    ) -> Result<FSG, Box<dyn Error>> {
+        let name = rule_name.trim_start_matches('<').trim_end_matches('>');
+        // Rules are stored with the name of their grammar
+        let name = if name.contains('.') {
+            name.to_string()
+        } else {
+            format!("{}.{}", self.get_name(), name)
+        };
+        let rule = self
+            .get_rule(&name)
+            .ok_or_else(|| format!("JSGF grammar has no rule named {}", name))?;
+        Ok(self.build_fsg(&rule, logmath, lw))
+    }
+
     /// Convert a JSGF rule to Sphinx FSG text form.
     ///
     /// This does a direct conversion without doing transitive closure on null transitions and so forth.
     pub fn write_fsg(&self, rule: &JSGFRule, path: &str) -> Result<(), Box<dyn Error>> {
-        let c_path = std::ffi::CString::new(path)?;
-        let c_file = unsafe { libc::fopen(c_path.as_ptr(), "rb".as_ptr() as *const i8) };
+        let c_path = to_cstring("path", path)?;
+        let c_file = unsafe { libc::fopen(c_path.as_ptr(), c"wb".as_ptr()) };
         if c_file.is_null() {
             return Err("Failed to open fsg output file".into());
         }
@@ -111,8 +140,245 @@ impl JSGF {
     }
 }
 
+/// Error returned when a JSGF grammar can not be parsed.
+///
+/// The pocketsphinx parser only logs the cause of an error, so the location is determined by checking the grammar for common structural mistakes (missing semicolons, unbalanced brackets).
+/// If none is found, the location points to the end of the grammar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsgfParseError {
+    /// Line of the error, starting at 1.
+    pub line: usize,
+    /// Column of the error, starting at 1.
+    pub column: usize,
+    /// Description of the error.
+    pub message: String,
+}
+
+impl JsgfParseError {
+    fn locate(grammar: &str) -> Self {
+        let mut line = 1;
+        let mut column = 0;
+        // Opening brackets with their position
+        let mut brackets: Vec<(char, usize, usize)> = Vec::new();
+        // Position of the first and last token of the current statement and whether it has an `=`
+        let mut statement_start: Option<(usize, usize)> = None;
+        let mut statement_end = (1, 1);
+        let mut statement_has_assignment = false;
+        let mut last_rule_name = (1, 1);
+        let mut chars = grammar.chars().peekable();
+        let mut in_line_comment = false;
+        let mut in_block_comment = false;
+        let mut in_quote = false;
+
+        let error = |line, column, message: &str| JsgfParseError {
+            line,
+            column,
+            message: message.to_string(),
+        };
+
+        while let Some(c) = chars.next() {
+            if c == '\n' {
+                line += 1;
+                column = 0;
+                in_line_comment = false;
+                continue;
+            }
+            column += 1;
+
+            if in_line_comment {
+                continue;
+            }
+            if in_block_comment {
+                if c == '*' && chars.peek() == Some(&'/') {
+                    chars.next();
+                    column += 1;
+                    in_block_comment = false;
+                }
+                continue;
+            }
+            if in_quote {
+                if c == '\\' {
+                    chars.next();
+                    column += 1;
+                } else if c == '"' {
+                    in_quote = false;
+                }
+                continue;
+            }
+            if c == '/' && chars.peek() == Some(&'/') {
+                in_line_comment = true;
+                continue;
+            }
+            if c == '/' && chars.peek() == Some(&'*') {
+                chars.next();
+                column += 1;
+                in_block_comment = true;
+                continue;
+            }
+            if c.is_whitespace() {
+                continue;
+            }
+
+            if statement_start.is_none() {
+                statement_start = Some((line, column));
+            }
+            statement_end = (line, column);
+
+            match c {
+                '"' => in_quote = true,
+                '(' | '[' | '<' => {
+                    if c == '<' && brackets.is_empty() {
+                        last_rule_name = (line, column);
+                    }
+                    brackets.push((c, line, column));
+                }
+                ')' | ']' | '>' => {
+                    let expected = match c {
+                        ')' => '(',
+                        ']' => '[',
+                        _ => '<',
+                    };
+                    match brackets.pop() {
+                        Some((open, _, _)) if open == expected => {}
+                        _ => return error(line, column, &format!("Unmatched '{}'", c)),
+                    }
+                }
+                '=' if brackets.is_empty() => {
+                    if statement_has_assignment {
+                        return error(
+                            last_rule_name.0,
+                            last_rule_name.1,
+                            "Missing ';' before rule definition",
+                        );
+                    }
+                    statement_has_assignment = true;
+                }
+                ';' => {
+                    if let Some((open, open_line, open_column)) = brackets.pop() {
+                        return error(open_line, open_column, &format!("Unclosed '{}'", open));
+                    }
+                    statement_start = None;
+                    statement_has_assignment = false;
+                }
+                _ => {}
+            }
+        }
+
+        if in_block_comment {
+            return error(line, column, "Unterminated comment");
+        }
+        if in_quote {
+            return error(line, column, "Unterminated quoted token");
+        }
+        if let Some((open, open_line, open_column)) = brackets.pop() {
+            return error(open_line, open_column, &format!("Unclosed '{}'", open));
+        }
+        if statement_start.is_some() {
+            return error(
+                statement_end.0,
+                statement_end.1,
+                "Missing ';' at end of statement",
+            );
+        }
+        error(line, column, "Failed to parse JSGF grammar")
+    }
+}
+
+impl std::fmt::Display for JsgfParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for JsgfParseError {}
+
 impl Drop for JSGF {
     fn drop(&mut self) {
         unsafe { pocketsphinx_sys::jsgf_grammar_free(self.inner) };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locate(grammar: &str) -> (usize, usize, String) {
+        let error = JsgfParseError::locate(grammar);
+        (error.line, error.column, error.message)
+    }
+
+    #[test]
+    fn unclosed_group_without_newline() {
+        let grammar = "#JSGF V1.0; grammar g; public <a> = (one | two;";
+        let column = grammar.find('(').unwrap() + 1;
+        assert_eq!(locate(grammar), (1, column, "Unclosed '('".to_string()));
+    }
+
+    #[test]
+    fn missing_semicolon_on_last_line() {
+        let grammar = "#JSGF V1.0;\ngrammar g;\npublic <a> = one two";
+        assert_eq!(
+            locate(grammar),
+            (3, 20, "Missing ';' at end of statement".to_string())
+        );
+    }
+
+    #[test]
+    fn missing_semicolon_before_rule() {
+        let grammar = "#JSGF V1.0;\ngrammar g;\npublic <a> = one\npublic <b> = two;\n";
+        assert_eq!(
+            locate(grammar),
+            (4, 8, "Missing ';' before rule definition".to_string())
+        );
+    }
+
+    #[test]
+    fn unmatched_closing_bracket() {
+        let grammar = "#JSGF V1.0;\ngrammar g;\npublic <a> = one ] two;\n";
+        assert_eq!(locate(grammar), (3, 18, "Unmatched ']'".to_string()));
+    }
+
+    #[test]
+    fn brackets_in_comments_and_quotes_are_ignored() {
+        let grammar = "#JSGF V1.0;\n// (\n/* [ */ grammar g;\npublic <a> = \"(\" two\n";
+        assert_eq!(
+            locate(grammar),
+            (4, 20, "Missing ';' at end of statement".to_string())
+        );
+    }
+
+    #[test]
+    fn unterminated_comment() {
+        let grammar = "#JSGF V1.0;\ngrammar g; /* no end";
+        assert_eq!(locate(grammar), (2, 20, "Unterminated comment".to_string()));
+    }
+
+    #[test]
+    fn display_includes_position() {
+        let error = JsgfParseError {
+            line: 3,
+            column: 7,
+            message: "Unclosed '('".to_string(),
+        };
+        assert_eq!(error.to_string(), "3:7: Unclosed '('");
+    }
+
+    #[test]
+    fn build_fsg_named_accepts_rule() {
+        let jsgf = JSGF::from_string(
+            "#JSGF V1.0; grammar robot; public <command> = <move> | stop; <move> = go (forward | back);",
+            None,
+        )
+        .unwrap();
+        let logmath = LogMath::default_for_decoding();
+
+        let fsg = jsgf.build_fsg_named("move", &logmath, 1.0).unwrap();
+        assert!(fsg.accept("go forward"));
+        assert!(!fsg.accept("stop"));
+        let fsg = jsgf
+            .build_fsg_named("<robot.command>", &logmath, 1.0)
+            .unwrap();
+        assert!(fsg.accept("stop"));
+        assert!(jsgf.build_fsg_named("turn", &logmath, 1.0).is_err());
+    }
+}
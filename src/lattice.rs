@@ -0,0 +1,214 @@
+use std::{collections::HashMap, error::Error, ffi::CStr, io::Write};
+
+use crate::{decoder::Decoder, ngram::Ngram};
+
+pub struct Lattice {
+    inner: *mut pocketsphinx_sys::ps_lattice_t,
+    retained: bool,
+}
+
+impl Lattice {
+    /// Get the word lattice of the current utterance from the decoder.
+    ///
+    /// The decoder owns the lattice and it is only valid until the next utterance is started, unless it is kept with `Lattice::retain()`.
+    pub fn from_decoder(decoder: &Decoder) -> Option<Self> {
+        let inner = unsafe { pocketsphinx_sys::ps_get_lattice(decoder.get_inner()) };
+        if inner.is_null() {
+            None
+        } else {
+            Some(Self {
+                inner,
+                retained: true,
+            })
+        }
+    }
+
+    /// Returns a retained lattice and assures the underlying pointer is not freed before the retained lattice is dropped.
+    ///
+    /// Use this to keep the lattice of an utterance beyond the start of the next one.
+    ///
+    /// # Returns
+    /// A new lattice with the same underlying pointer.
+    pub fn retain(&self) -> Self {
+        let retained_inner = unsafe { pocketsphinx_sys::ps_lattice_retain(self.inner) };
+        Self {
+            inner: retained_inner,
+            retained: false,
+        }
+    }
+
+    /// Get the number of frames in the lattice.
+    pub fn n_frames(&self) -> i32 {
+        unsafe { pocketsphinx_sys::ps_lattice_n_frames(self.inner) }
+    }
+
+    /// Get the number of nodes in the lattice.
+    pub fn n_nodes(&self) -> usize {
+        let mut n_nodes = 0;
+        let mut node_iter = unsafe { pocketsphinx_sys::ps_latnode_iter(self.inner) };
+        while !node_iter.is_null() {
+            n_nodes += 1;
+            // Frees the iterator when the end is reached
+            node_iter = unsafe { pocketsphinx_sys::ps_latnode_iter_next(node_iter) };
+        }
+        n_nodes
+    }
+
+    /// Find the best word sequence through the lattice.
+    ///
+    /// This can be used to recompute the best path after changing the language model or the weights, independent of `Decoder::get_hyp()`.
+    ///
+    /// # Arguments
+    /// - `lm` - Language model to use for rescoring. If `None`, the scores already stored in the lattice are used.
+    /// - `lwf` - Language model weight factor relative to the weight used during decoding (`1.0` keeps it unchanged).
+    /// - `ascale` - Scaling factor applied to acoustic scores (`1.0` keeps them unchanged).
+    ///
+    /// # Returns
+    /// A tuple of the best hypothesis and the log posterior probability of its final word or `None` if no path could be found.
+    pub fn best_hypothesis(
+        &self,
+        lm: Option<&Ngram>,
+        lwf: f32,
+        ascale: f32,
+    ) -> Option<(String, i32)> {
+        let lm_ptr = lm.map_or(std::ptr::null_mut(), |lm| lm.get_inner());
+        let link =
+            unsafe { pocketsphinx_sys::ps_lattice_bestpath(self.inner, lm_ptr, lwf, ascale) };
+        if link.is_null() {
+            return None;
+        }
+
+        let c_hyp = unsafe { pocketsphinx_sys::ps_lattice_hyp(self.inner, link) };
+        if c_hyp.is_null() {
+            return None;
+        }
+        let hyp = unsafe { std::ffi::CStr::from_ptr(c_hyp) }
+            .to_string_lossy()
+            .into_owned();
+        let score =
+            unsafe { pocketsphinx_sys::ps_latlink_prob(self.inner, link, std::ptr::null_mut()) };
+        Some((hyp, score))
+    }
+
+    /// Write the lattice in the Graphviz DOT format.
+    ///
+    /// Nodes are labeled with their word and start frame, links with their acoustic score and log posterior probability.
+    ///
+    /// # Arguments
+    /// - `w` - Writer receiving the graph.
+    pub fn write_dot<W: Write>(&self, w: &mut W) -> Result<(), Box<dyn Error>> {
+        let mut nodes = Vec::new();
+        let mut node_iter = unsafe { pocketsphinx_sys::ps_latnode_iter(self.inner) };
+        while !node_iter.is_null() {
+            nodes.push(unsafe { pocketsphinx_sys::ps_latnode_iter_node(node_iter) });
+            // Frees the iterator when the end is reached
+            node_iter = unsafe { pocketsphinx_sys::ps_latnode_iter_next(node_iter) };
+        }
+        let node_ids: HashMap<_, _> = nodes.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+
+        writeln!(w, "digraph lattice {{")?;
+        writeln!(w, "  rankdir=LR;")?;
+        for (id, node) in nodes.iter().enumerate() {
+            let word =
+                unsafe { CStr::from_ptr(pocketsphinx_sys::ps_latnode_word(self.inner, *node)) }
+                    .to_string_lossy();
+            let start = unsafe {
+                pocketsphinx_sys::ps_latnode_times(
+                    *node,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                )
+            };
+            writeln!(w, "  n{} [label=\"{}/{}\"];", id, escape_dot(&word), start)?;
+        }
+        for (id, node) in nodes.iter().enumerate() {
+            let mut link_iter = unsafe { pocketsphinx_sys::ps_latnode_exits(*node) };
+            while !link_iter.is_null() {
+                let link = unsafe { pocketsphinx_sys::ps_latlink_iter_link(link_iter) };
+                let dest =
+                    unsafe { pocketsphinx_sys::ps_latlink_nodes(link, std::ptr::null_mut()) };
+                let mut ascr = 0;
+                let prob =
+                    unsafe { pocketsphinx_sys::ps_latlink_prob(self.inner, link, &mut ascr) };
+                if let Some(dest_id) = node_ids.get(&dest) {
+                    writeln!(
+                        w,
+                        "  n{} -> n{} [label=\"{},{}\"];",
+                        id, dest_id, ascr, prob
+                    )?;
+                }
+                link_iter = unsafe { pocketsphinx_sys::ps_latlink_iter_next(link_iter) };
+            }
+        }
+        writeln!(w, "}}")?;
+        Ok(())
+    }
+
+    pub fn get_inner(&self) -> *mut pocketsphinx_sys::ps_lattice_t {
+        self.inner
+    }
+}
+
+impl Drop for Lattice {
+    fn drop(&mut self) {
+        if !self.retained {
+            unsafe { pocketsphinx_sys::ps_lattice_free(self.inner) };
+        }
+    }
+}
+
+fn escape_dot(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::{decode_utterance, default_decoder, goforward_samples};
+
+    #[test]
+    fn best_hypothesis_matches_decoder() {
+        let mut decoder = default_decoder(&[]);
+        let (hyp, _) = decode_utterance(&mut decoder, &goforward_samples()).unwrap();
+        let lattice = decoder.get_lattice().unwrap();
+
+        let (best, prob) = lattice.best_hypothesis(None, 1.0, 1.0).unwrap();
+        assert_eq!(best, hyp);
+        assert!(prob <= 0);
+    }
+
+    #[test]
+    fn write_dot_lists_nodes_and_links() {
+        let mut decoder = default_decoder(&[]);
+        decode_utterance(&mut decoder, &goforward_samples()).unwrap();
+        let lattice = decoder.get_lattice().unwrap();
+
+        let mut dot = Vec::new();
+        lattice.write_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.starts_with("digraph lattice {\n"));
+        assert!(dot.ends_with("}\n"));
+        let n_nodes = dot
+            .lines()
+            .filter(|line| line.contains("[label=\"") && !line.contains("->"))
+            .count();
+        assert_eq!(n_nodes, lattice.n_nodes());
+        assert!(dot.lines().any(|line| line.contains("->")));
+        assert!(dot.contains("[label=\"forward"));
+    }
+
+    #[test]
+    fn retained_lattice_survives_next_utterance() {
+        let mut decoder = default_decoder(&[]);
+        let samples = goforward_samples();
+        decode_utterance(&mut decoder, &samples).unwrap();
+        let lattice = decoder.get_lattice().unwrap().retain();
+        let n_frames = lattice.n_frames();
+        let n_nodes = lattice.n_nodes();
+        assert!(n_frames > 0);
+        assert!(n_nodes > 0);
+
+        decode_utterance(&mut decoder, &samples).unwrap();
+        assert_eq!(lattice.n_frames(), n_frames);
+        assert_eq!(lattice.n_nodes(), n_nodes);
+    }
+}
@@ -1,17 +1,31 @@
 use std::ffi::CStr;
 
 pub mod alignment_iter;
+pub mod audio;
 pub mod config;
 pub mod decoder;
 pub mod endpointer;
+pub mod error;
 pub mod fsg;
 pub mod jsgf;
 pub mod jsgf_rule_iter;
+pub mod lattice;
 pub mod logmath;
+pub mod metrics;
+pub mod mllr;
 pub mod nbest_iter;
+pub mod pcm_sink;
 pub mod search_iter;
 pub mod seg_iter;
+pub mod shared_decoder;
+pub mod streaming;
+pub mod subtitle;
+#[cfg(test)]
+mod test_util;
+pub mod tokenizer;
+pub mod transcribe;
 pub mod vad;
+pub mod wakeword;
 
 pub mod ngram;
 pub mod ngram_iter;
@@ -19,17 +33,29 @@ pub mod ngram_set_iter;
 
 // Reexport all the modules such that they can be accessed via pocketsphinx::*
 pub use alignment_iter::*;
+pub use audio::*;
 pub use config::*;
 pub use decoder::*;
 pub use endpointer::*;
+pub use error::*;
 pub use fsg::*;
 pub use jsgf::*;
 pub use jsgf_rule_iter::*;
+pub use lattice::*;
 pub use logmath::*;
+pub use metrics::*;
+pub use mllr::*;
 pub use nbest_iter::*;
+pub use pcm_sink::*;
 pub use search_iter::*;
 pub use seg_iter::*;
+pub use shared_decoder::*;
+pub use streaming::*;
+pub use subtitle::*;
+pub use tokenizer::*;
+pub use transcribe::*;
 pub use vad::*;
+pub use wakeword::*;
 
 pub use ngram::*;
 pub use ngram_iter::*;
@@ -1,6 +1,10 @@
-use std::{error::Error, ffi::CString};
+use std::error::Error;
 
 use crate::decoder::Decoder;
+use crate::error::to_cstring;
+
+/// Default value of the `-logbase` configuration parameter used by the decoder.
+const DEFAULT_LOGBASE: f64 = 1.0001;
 
 pub struct LogMath {
     inner: *mut pocketsphinx_sys::logmath_t,
@@ -23,6 +27,13 @@ impl LogMath {
         }
     }
 
+    /// Initialize a log math computation table with the decoder's default parameters.
+    ///
+    /// FSGs built from JSGF grammars with this logmath score consistently with a decoder that uses the default `-logbase`.
+    pub fn default_for_decoding() -> Self {
+        Self::new(DEFAULT_LOGBASE, 0, true)
+    }
+
     /// Get logmath from decoder.
     pub fn from_decoder(decoder: &Decoder) -> Self {
         let inner = unsafe { pocketsphinx_sys::ps_get_logmath(decoder.get_inner()) };
@@ -38,7 +49,7 @@ impl LogMath {
     /// # Arguments
     /// - `path` - Path to the log table file.
     pub fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
-        let path = CString::new(path)?;
+        let path = to_cstring("path", path)?;
         let inner = unsafe { pocketsphinx_sys::logmath_read(path.as_ptr()) };
         if inner.is_null() {
             Err("Failed to read logmath from file".into())
@@ -56,7 +67,7 @@ impl LogMath {
     /// # Arguments
     /// - `path` - Path to the log table file to write to.
     pub fn write_to_file(&self, path: &str) -> Result<i32, Box<dyn Error>> {
-        let path = CString::new(path)?;
+        let path = to_cstring("path", path)?;
         Ok(unsafe { pocketsphinx_sys::logmath_write(self.inner, path.as_ptr()) })
     }
 
@@ -105,10 +116,8 @@ impl LogMath {
     ///
     /// # Returns
     /// A new Logmath instance with the same inner pointer.
-    pub fn retain(&mut self) -> Self {
+    pub fn retain(&self) -> Self {
         let retained_inner = unsafe { pocketsphinx_sys::logmath_retain(self.inner) };
-        self.retained = true;
-
         Self {
             inner: retained_inner,
             retained: false,
@@ -139,6 +148,14 @@ impl LogMath {
         unsafe { pocketsphinx_sys::logmath_exp(self.inner, logb_p) }
     }
 
+    /// Convert integer log in base B to a percentage, e.g. to display a confidence.
+    ///
+    /// # Returns
+    /// The linear probability multiplied by 100 and clamped to the range [0, 100].
+    pub fn to_percent(&self, score: i32) -> f64 {
+        (self.exp(score) * 100.0).clamp(0.0, 100.0)
+    }
+
     /// Convert natural log (in floating point) to integer log in base B.
     /// @see https://cmusphinx.github.io/doc/pocketsphinx/structlogmath__t.html#a66303cb0b91f43452ea500f53ef7eaa5
     pub fn ln_to_log(&self, log_p: f64) -> i32 {
@@ -195,3 +212,43 @@ pub struct LogMathTableShape {
     pub width: u32,
     pub shift: u32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::default_decoder;
+
+    #[test]
+    fn default_for_decoding_matches_decoder_logbase() {
+        let decoder = default_decoder(&[]);
+        let logbase = decoder.get_config().get_float("logbase").unwrap();
+        let logmath = LogMath::default_for_decoding();
+        assert_eq!(logmath.get_base(), logbase);
+        assert_eq!(logmath.get_base(), decoder.get_logmath().get_base());
+    }
+
+    #[test]
+    fn to_percent_of_confidence() {
+        let decoder = default_decoder(&[]);
+        let logmath = decoder.get_logmath();
+        assert!((logmath.to_percent(logmath.log(0.5)) - 50.0).abs() < 0.1);
+        assert!((logmath.to_percent(0) - 100.0).abs() < 1e-6);
+        assert_eq!(logmath.to_percent(logmath.get_zero()), 0.0);
+    }
+
+    #[test]
+    fn retained_handle_outlives_original() {
+        let logmath = LogMath::default_for_decoding();
+        let retained = logmath.retain();
+        let half = logmath.log(0.5);
+        drop(logmath);
+        assert_eq!(retained.log(0.5), half);
+    }
+
+    #[test]
+    fn original_handle_outlives_retained() {
+        let logmath = LogMath::default_for_decoding();
+        drop(logmath.retain());
+        assert!((logmath.exp(logmath.log(0.5)) - 0.5).abs() < 1e-3);
+    }
+}
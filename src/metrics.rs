@@ -0,0 +1,83 @@
+/// Compute the edit distance between two phone sequences.
+///
+/// Substitutions, insertions and deletions of single phones all count as one edit.
+///
+/// # Arguments
+/// - `a` - First phone sequence, e.g. from `Alignment::phone_sequence`.
+/// - `b` - Second phone sequence.
+///
+/// # Returns
+/// The minimum number of edits needed to turn `a` into `b`.
+pub fn phone_edit_distance(a: &[String], b: &[String]) -> usize {
+    // Only the previous row of the distance matrix is needed
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, phone_a) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, phone_b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(phone_a != phone_b);
+            let deletion = previous[j + 1] + 1;
+            let insertion = current[j] + 1;
+            current[j + 1] = substitution.min(deletion).min(insertion);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phones(s: &str) -> Vec<String> {
+        s.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn identical_sequences() {
+        assert_eq!(
+            phone_edit_distance(&phones("HH AH L OW"), &phones("HH AH L OW")),
+            0
+        );
+    }
+
+    #[test]
+    fn insertion() {
+        assert_eq!(
+            phone_edit_distance(&phones("HH L OW"), &phones("HH AH L OW")),
+            1
+        );
+    }
+
+    #[test]
+    fn deletion() {
+        assert_eq!(
+            phone_edit_distance(&phones("HH AH L OW"), &phones("HH AH OW")),
+            1
+        );
+    }
+
+    #[test]
+    fn substitution() {
+        assert_eq!(
+            phone_edit_distance(&phones("HH AH L OW"), &phones("HH EH L OW")),
+            1
+        );
+    }
+
+    #[test]
+    fn empty_sequences() {
+        assert_eq!(phone_edit_distance(&[], &[]), 0);
+        assert_eq!(phone_edit_distance(&phones("HH AH"), &[]), 2);
+        assert_eq!(phone_edit_distance(&[], &phones("HH AH L")), 3);
+    }
+
+    #[test]
+    fn mixed_edits() {
+        // Substitute K -> S, T -> SH and IH -> Z
+        assert_eq!(
+            phone_edit_distance(&phones("K AE T IH"), &phones("S AE SH Z")),
+            3
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use std::error::Error;
+
+use crate::error::to_cstring;
+
+/// Linear transform (MLLR) for speaker adaptation of the acoustic model.
+pub struct MLLR {
+    inner: *mut pocketsphinx_sys::ps_mllr_t,
+    retained: bool,
+}
+
+impl MLLR {
+    /// Read a speaker-adaptive linear transform from a file, e.g. an `mllr_matrix` created during acoustic model adaptation.
+    ///
+    /// # Arguments
+    /// - `path` - Path to the MLLR transformation file.
+    pub fn read(path: &str) -> Result<Self, Box<dyn Error>> {
+        let c_path = to_cstring("path", path)?;
+        let inner = unsafe { pocketsphinx_sys::ps_mllr_read(c_path.as_ptr()) };
+        if inner.is_null() {
+            Err(format!("Failed to read MLLR transform from {}", path).into())
+        } else {
+            Ok(Self {
+                inner,
+                retained: false,
+            })
+        }
+    }
+
+    /// Returns a retained MLLR transform and assures the underlying pointer is not freed before the retained transform is dropped.
+    ///
+    /// # Returns
+    /// A new MLLR transform with the same underlying pointer.
+    pub fn retain(&self) -> Self {
+        let retained_inner = unsafe { pocketsphinx_sys::ps_mllr_retain(self.inner) };
+        Self {
+            inner: retained_inner,
+            retained: false,
+        }
+    }
+
+    pub fn get_inner(&self) -> *mut pocketsphinx_sys::ps_mllr_t {
+        self.inner
+    }
+}
+
+impl Drop for MLLR {
+    fn drop(&mut self) {
+        if !self.retained {
+            unsafe { pocketsphinx_sys::ps_mllr_free(self.inner) };
+        }
+    }
+}
@@ -1,5 +1,19 @@
+use std::marker::PhantomData;
+
 use crate::{decoder::Decoder, seg_iter::SegIter};
 
+/// Iterator over the N-best hypotheses of an utterance.
+///
+/// Advancing the iterator frees the previous entry, so `NBestIter::next()` lends out an `NBest` that borrows the iterator and has to be dropped before advancing again:
+///
+/// ```compile_fail,E0499
+/// # fn nbest(decoder: &pocketsphinx::Decoder) {
+/// let mut nbest_iter = decoder.get_nbest_iter().unwrap();
+/// let first = nbest_iter.next().unwrap();
+/// nbest_iter.next();
+/// first.get_seg();
+/// # }
+/// ```
 pub struct NBestIter {
     inner: *mut pocketsphinx_sys::ps_nbest_t,
     reached_end: bool,
@@ -10,7 +24,7 @@ impl NBestIter {
     pub fn from_decoder(decoder: &Decoder) -> Option<Self> {
         let inner = unsafe { pocketsphinx_sys::ps_nbest(decoder.get_inner()) };
         if inner.is_null() {
-            return None;
+            None
         } else {
             Some(Self {
                 inner,
@@ -19,12 +33,13 @@ impl NBestIter {
             })
         }
     }
-}
 
-impl Iterator for NBestIter {
-    type Item = NBest;
-
-    fn next(&mut self) -> Option<Self::Item> {
+    /// Advance to the next hypothesis.
+    ///
+    /// # Returns
+    /// The next entry, which borrows the iterator, or `None` at the end of the list.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<NBest<'_>> {
         // Skip the first call to ps_nbest_next in order to get the first segment
         if self.is_initial {
             self.is_initial = false;
@@ -40,9 +55,24 @@ impl Iterator for NBestIter {
             return None;
         }
 
-        let nbest = NBest { inner: self.inner };
+        let nbest = NBest {
+            inner: self.inner,
+            _iter: PhantomData,
+        };
         Some(nbest)
     }
+
+    /// Get the hypotheses of up to `n` entries.
+    pub fn take_hypotheses(mut self, n: usize) -> Vec<NBestHypothesis> {
+        let mut hypotheses = Vec::new();
+        while hypotheses.len() < n {
+            match self.next() {
+                Some(nbest) => hypotheses.push(nbest.get_hyp()),
+                None => break,
+            }
+        }
+        hypotheses
+    }
 }
 
 impl Drop for NBestIter {
@@ -53,29 +83,30 @@ impl Drop for NBestIter {
     }
 }
 
-pub struct NBest {
+/// Entry of an N-best list, valid until its `NBestIter` is advanced.
+pub struct NBest<'a> {
     inner: *mut pocketsphinx_sys::ps_nbest_t,
+    _iter: PhantomData<&'a mut NBestIter>,
 }
 
-impl NBest {
+impl NBest<'_> {
     /// Get the hypothesis from an N-best list iterator.
     pub fn get_hyp(&self) -> NBestHypothesis {
         let mut score = 0;
         let c_hyp = unsafe { pocketsphinx_sys::ps_nbest_hyp(self.inner, &mut score) };
-        let hypothesis;
-        if c_hyp.is_null() {
-            hypothesis = "".to_string();
+        let hypothesis = if c_hyp.is_null() {
+            "".to_string()
         } else {
-            hypothesis = unsafe { std::ffi::CStr::from_ptr(c_hyp) }
+            unsafe { std::ffi::CStr::from_ptr(c_hyp) }
                 .to_str()
                 .unwrap()
-                .to_string();
-        }
+                .to_string()
+        };
         NBestHypothesis { hypothesis, score }
     }
 
     /// Get the word segmentation from the N-best.
-    pub fn get_seg(&self) -> SegIter {
+    pub fn get_seg(&self) -> SegIter<'_> {
         SegIter::from_nbest(self)
     }
 
@@ -84,9 +115,21 @@ impl NBest {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct NBestHypothesis {
     /// Hypothesis string from N-best list iterator.
     pub hypothesis: String,
     /// Path score for this hypothesis.
     pub score: i32,
 }
+
+/// N-best hypothesis with its posterior probability, see `Decoder::nbest_with_posteriors()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NBestResult {
+    /// Hypothesis string.
+    pub hypothesis: String,
+    /// Path score of the hypothesis.
+    pub score: i32,
+    /// Posterior probability of the hypothesis relative to the other N-best hypotheses.
+    pub posterior: f64,
+}
@@ -3,7 +3,7 @@ use std::{
     ffi::{c_char, CStr, CString},
 };
 
-use crate::{Config, Decoder, LogMath, NgramIter, NgramSetIter};
+use crate::{Config, Decoder, LogMath, NgramIter, NgramSetIter, Tokenizer, WhitespaceTokenizer};
 
 pub struct Ngram {
     inner: *mut pocketsphinx_sys::ngram_model_t,
@@ -121,9 +121,8 @@ impl Ngram {
     ///
     /// # Returns
     /// A new `Ngram` with the same underlying pointer.
-    pub fn retain(&mut self) -> Self {
+    pub fn retain(&self) -> Self {
         let retained_inner = unsafe { pocketsphinx_sys::ngram_model_retain(self.inner) };
-        self.retained = true;
         Self {
             inner: retained_inner,
             retained: false,
@@ -132,8 +131,16 @@ impl Ngram {
 
     /// Case-fold word strings in an N-Gram model.
     /// WARNING: This is not Unicode aware, so any non-ASCII characters will not be converted.
-    pub fn casefold(&self, kase: i32) -> i32 {
-        unsafe { pocketsphinx_sys::ngram_model_casefold(self.inner, kase) }
+    ///
+    /// # Arguments
+    /// - `kase` - `0` to fold to upper case, `1` to fold to lower case.
+    pub fn casefold(&self, kase: i32) -> Result<(), Box<dyn Error>> {
+        let result = unsafe { pocketsphinx_sys::ngram_model_casefold(self.inner, kase) };
+        if result == 0 {
+            Ok(())
+        } else {
+            Err("Failed to casefold N-Gram model".into())
+        }
     }
 
     /// Apply a language weight, insertion penalty, and unigram weight to a language model.
@@ -180,6 +187,37 @@ impl Ngram {
         unsafe { pocketsphinx_sys::ngram_score(self.inner, c_words.as_ptr() as *const _) }
     }
 
+    /// Get the score for a general N-Gram from already resolved word IDs.
+    ///
+    /// Like `Ngram::score()`, `ids` holds the word followed by its history words in reverse order, but as word IDs (see `Ngram::wid()`).
+    /// No word lookups are done, so this is the function to use when rescoring in hot loops.
+    ///
+    /// ```rust
+    /// let ids = ["joy", "whole", "a"].map(|w| ngram.wid(w));
+    /// let score = ngram.score_ids(&ids);
+    /// ```
+    ///
+    /// # Returns
+    /// The score for the N-Gram or `Ngram::zero()` if `ids` is empty.
+    pub fn score_ids(&self, ids: &[i32]) -> i32 {
+        let (wid, history) = match ids.split_first() {
+            Some(split) => split,
+            None => return self.zero(),
+        };
+        let mut n_used = 0;
+        // ngram_ng_score may rewrite class words in the history, so pass a copy
+        let mut history = history.to_vec();
+        unsafe {
+            pocketsphinx_sys::ngram_ng_score(
+                self.inner,
+                *wid,
+                history.as_mut_ptr(),
+                history.len() as i32,
+                &mut n_used,
+            )
+        }
+    }
+
     /// Quick trigram score lookup.
     pub fn tg_score(&self, w3: i32, w2: i32, w1: i32, n_used: &mut i32) -> i32 {
         unsafe { pocketsphinx_sys::ngram_tg_score(self.inner, w3, w2, w1, n_used) }
@@ -253,6 +291,54 @@ impl Ngram {
         }
     }
 
+    /// Get the "raw" log-probability of a complete sentence.
+    ///
+    /// The sentence is wrapped in the sentence boundary markers `<s>` and `</s>` and the probabilities of all words following `<s>` are summed up,
+    /// each conditioned on as many preceding words as the order of the model allows.
+    ///
+    /// ```rust
+    /// let logprob = ngram.sentence_logprob("go forward ten meters");
+    /// ```
+    ///
+    /// # Arguments
+    /// - `sentence` - Words of the sentence separated by whitespace, without boundary markers.
+    ///
+    /// # Returns
+    /// The total log-probability in the base of the model's logmath.
+    pub fn sentence_logprob(&self, sentence: &str) -> i32 {
+        self.sentence_logprob_with(sentence, &WhitespaceTokenizer)
+    }
+
+    /// Get the "raw" log-probability of a complete sentence, splitting it into words with a custom tokenizer.
+    ///
+    /// See `Ngram::sentence_logprob()`.
+    ///
+    /// # Arguments
+    /// - `sentence`  - Sentence without boundary markers.
+    /// - `tokenizer` - Tokenizer splitting the sentence into words of the model.
+    pub fn sentence_logprob_with(&self, sentence: &str, tokenizer: &dyn Tokenizer) -> i32 {
+        let words = tokenizer.tokenize(sentence);
+        let ids: Vec<i32> = std::iter::once("<s>")
+            .chain(words.iter().map(|word| word.as_str()))
+            .chain(std::iter::once("</s>"))
+            .map(|word| self.wid(word))
+            .collect();
+        let max_history = self.get_counts().len().saturating_sub(1);
+
+        let mut total: i32 = 0;
+        for i in 1..ids.len() {
+            // The history holds the preceding words in reverse order
+            let mut history: Vec<i32> = ids[i.saturating_sub(max_history)..i]
+                .iter()
+                .rev()
+                .copied()
+                .collect();
+            let mut n_used = 0;
+            total = total.saturating_add(self.ng_prob(ids[i], &mut history, &mut n_used));
+        }
+        total
+    }
+
     /// Convert score to "raw" log-probability.
     ///
     /// Note: The unigram weight (interpolation with uniform) is not removed, since there is no way to know which order of N-Gram generated score.
@@ -307,7 +393,7 @@ impl Ngram {
         let counts = unsafe { pocketsphinx_sys::ngram_model_get_counts(self.inner) };
         let mut counts_vec = Vec::new();
         for i in 0..self.get_size() as usize {
-            counts_vec.push(unsafe { *counts.offset(i as isize) });
+            counts_vec.push(unsafe { *counts.add(i) });
         }
         counts_vec
     }
@@ -324,7 +410,12 @@ impl Ngram {
         if inner.is_null() {
             None
         } else {
-            Some(NgramIter::from_inner(inner))
+            let count = self.get_counts().get(m as usize).copied().unwrap_or(0);
+            Some(
+                NgramIter::from_inner(inner)
+                    .with_len(count as usize)
+                    .with_order(m as usize + 1),
+            )
         }
     }
 
@@ -338,7 +429,7 @@ impl Ngram {
         c_words.push(std::ptr::null());
         let inner =
             unsafe { pocketsphinx_sys::ngram_iter(self.inner, c_words.as_ptr() as *const _) };
-        NgramIter::from_inner(inner)
+        NgramIter::from_inner(inner).with_order(words.len())
     }
 
     /// Get an iterator over M-grams pointing to the specified M-gram.
@@ -351,7 +442,7 @@ impl Ngram {
                 history.len() as i32,
             )
         };
-        NgramIter::from_inner(inner)
+        NgramIter::from_inner(inner).with_order(history.len() + 1)
     }
 
     /// Add a word (unigram) to the language model.
@@ -540,7 +631,7 @@ impl Ngram {
         if inner.is_null() {
             None
         } else {
-            Some(NgramSetIter::from_inner(inner))
+            Some(NgramSetIter::from_inner(inner).with_len(self.set_count() as usize))
         }
     }
 
@@ -557,7 +648,7 @@ impl Ngram {
         } else {
             Some(Ngram {
                 inner,
-                retained: false,
+                retained: true,
             })
         }
     }
@@ -575,7 +666,7 @@ impl Ngram {
         } else {
             Some(Ngram {
                 inner,
-                retained: false,
+                retained: true,
             })
         }
     }
@@ -603,7 +694,7 @@ impl Ngram {
         };
         Ngram {
             inner,
-            retained: false,
+            retained: true,
         }
     }
 
@@ -627,7 +718,7 @@ impl Ngram {
         };
         Ngram {
             inner,
-            retained: false,
+            retained: true,
         }
     }
 
@@ -719,3 +810,99 @@ impl NgramFileType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Ngram;
+    use crate::test_util::default_decoder;
+
+    #[test]
+    fn score_ids_matches_ng_score() {
+        let lm = default_decoder(&[]).get_lm(None).unwrap();
+        let ids = ["meters", "ten", "forward"].map(|w| lm.wid(w));
+        let score = lm.score_ids(&ids);
+
+        let mut history = ids[1..].to_vec();
+        let mut n_used = 0;
+        assert_eq!(score, lm.ng_score(ids[0], &mut history, &mut n_used));
+        assert_eq!(lm.score_ids(&[]), lm.zero());
+    }
+
+    #[test]
+    fn mgrams_size_hint_counts_down() {
+        let lm = default_decoder(&[]).get_lm(None).unwrap();
+        let n_unigrams = lm.get_counts()[0] as usize;
+        let mut unigrams = lm.mgrams(0).unwrap();
+        assert_eq!(unigrams.size_hint(), (n_unigrams, Some(n_unigrams)));
+
+        unigrams.next().unwrap();
+        assert_eq!(unigrams.size_hint(), (n_unigrams - 1, Some(n_unigrams - 1)));
+        assert_eq!(unigrams.count(), n_unigrams - 1);
+    }
+
+    #[test]
+    fn set_iter_size_hint_matches_set_count() {
+        let decoder = default_decoder(&[]);
+        let lm = decoder.get_lm(None).unwrap();
+        let set = Ngram::set_init(
+            &decoder.get_config(),
+            &[lm.retain(), lm.retain()],
+            &["first", "second"],
+            None,
+        );
+        let iter = set.set_iter().unwrap();
+        assert_eq!(iter.size_hint(), (2, Some(2)));
+        assert_eq!(iter.count(), 2);
+    }
+
+    #[test]
+    fn casefold_changes_word_strings() {
+        let lm = default_decoder(&[]).get_lm(None).unwrap();
+        let unknown = lm.unknown_wid();
+        let wid = lm.wid("forward");
+        assert_ne!(wid, unknown);
+
+        lm.casefold(0).unwrap();
+        assert_eq!(lm.wid("FORWARD"), wid);
+        assert_eq!(lm.wid("forward"), unknown);
+
+        lm.casefold(1).unwrap();
+        assert_eq!(lm.wid("forward"), wid);
+    }
+
+    #[test]
+    fn successor_words_are_bigrams() {
+        let lm = default_decoder(&[]).get_lm(None).unwrap();
+        let go = lm.wid("go");
+        // The item is only valid while its iterator is alive
+        let mut unigrams = lm.mgrams(0).unwrap();
+        let unigram = unigrams.find(|unigram| unigram.get().0 == [go]).unwrap();
+
+        let successors = unigram.successor_words(&lm);
+        assert!(!successors.is_empty());
+        for (word, _score) in successors.iter().take(10) {
+            let wid = lm.wid(word);
+            assert_ne!(wid, lm.unknown_wid());
+            let mut n_used = 0;
+            lm.bg_score(wid, go, &mut n_used);
+            assert_eq!(n_used, 2, "go {} is not a bigram", word);
+        }
+    }
+
+    #[test]
+    fn sentence_logprob_with_custom_tokenizer() {
+        struct CommaTokenizer;
+
+        impl crate::Tokenizer for CommaTokenizer {
+            fn tokenize(&self, text: &str) -> Vec<String> {
+                text.split(',').map(|word| word.to_string()).collect()
+            }
+        }
+
+        let lm = default_decoder(&[]).get_lm(None).unwrap();
+        assert_eq!(
+            lm.sentence_logprob_with("go,forward,ten,meters", &CommaTokenizer),
+            lm.sentence_logprob("go forward ten meters")
+        );
+    }
+}
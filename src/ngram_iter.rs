@@ -1,5 +1,8 @@
+use crate::ngram::Ngram;
+
 pub struct NgramIterItem {
     inner: *mut pocketsphinx_sys::ngram_iter_t,
+    order: Option<usize>,
 }
 
 /// M-gram (yes, M-gram) iterator object.
@@ -16,20 +19,46 @@ impl NgramIterItem {
         let mut bowt = 0;
         let word_ids =
             unsafe { pocketsphinx_sys::ngram_iter_get(self.inner, &mut score, &mut bowt) };
-        // Convert word_ids to a Vec<i32>
-        let mut word_ids_vec = Vec::new();
-        let mut i = 0;
-        while unsafe { *word_ids.offset(i) } != -1 {
-            word_ids_vec.push(unsafe { *word_ids.offset(i) });
-            i += 1;
-        }
+        // The array holds exactly one word ID per word of the M-gram and is not terminated
+        let word_ids_vec = match self.order {
+            Some(order) => unsafe { std::slice::from_raw_parts(word_ids, order) }.to_vec(),
+            None => {
+                let mut word_ids_vec = Vec::new();
+                let mut i = 0;
+                while unsafe { *word_ids.add(i) } != -1 {
+                    word_ids_vec.push(unsafe { *word_ids.add(i) });
+                    i += 1;
+                }
+                word_ids_vec
+            }
+        };
         (word_ids_vec, score, bowt)
     }
 
     /// Iterate over all M-gram successors of an M-1-gram.
     pub fn successors(&self) -> NgramIter {
         let inner = unsafe { pocketsphinx_sys::ngram_iter_successors(self.inner) };
-        NgramIter::from_inner(inner)
+        let iter = NgramIter::from_inner(inner);
+        match self.order {
+            Some(order) => iter.with_order(order + 1),
+            None => iter,
+        }
+    }
+
+    /// Collect the words following this M-gram together with their scores.
+    ///
+    /// # Arguments
+    /// - `model` - The language model this iterator was created from, used to resolve word IDs.
+    ///
+    /// # Returns
+    /// A vector of (word, score) tuples for all successors.
+    pub fn successor_words(&self, model: &Ngram) -> Vec<(String, i32)> {
+        self.successors()
+            .filter_map(|successor| {
+                let (word_ids, score, _bowt) = successor.get();
+                word_ids.last().map(|wid| (model.word(*wid), score))
+            })
+            .collect()
     }
 }
 
@@ -40,6 +69,8 @@ pub struct NgramIter {
     inner: *mut pocketsphinx_sys::ngram_iter_t,
     reached_end: bool,
     is_initial: bool,
+    remaining: Option<usize>,
+    order: Option<usize>,
 }
 
 impl NgramIter {
@@ -48,8 +79,22 @@ impl NgramIter {
             inner,
             reached_end: false,
             is_initial: true,
+            remaining: None,
+            order: None,
         }
     }
+
+    /// Set the number of items this iterator is known to yield, which is reported by `size_hint`.
+    pub(crate) fn with_len(mut self, len: usize) -> Self {
+        self.remaining = Some(len);
+        self
+    }
+
+    /// Set the number of words of the M-grams this iterator yields, which is needed to read their word IDs.
+    pub(crate) fn with_order(mut self, order: usize) -> Self {
+        self.order = Some(order);
+        self
+    }
 }
 
 impl Iterator for NgramIter {
@@ -71,9 +116,25 @@ impl Iterator for NgramIter {
             return None;
         }
 
-        let ngram = NgramIterItem { inner: self.inner };
+        if let Some(remaining) = self.remaining.as_mut() {
+            *remaining = remaining.saturating_sub(1);
+        }
+        let ngram = NgramIterItem {
+            inner: self.inner,
+            order: self.order,
+        };
         Some(ngram)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.reached_end {
+            return (0, Some(0));
+        }
+        match self.remaining {
+            Some(remaining) => (remaining, Some(remaining)),
+            None => (0, None),
+        }
+    }
 }
 
 impl Drop for NgramIter {
@@ -28,6 +28,7 @@ pub struct NgramSetIter {
     inner: *mut pocketsphinx_sys::ngram_model_set_iter_t,
     reached_end: bool,
     is_initial: bool,
+    remaining: Option<usize>,
 }
 
 impl NgramSetIter {
@@ -36,8 +37,15 @@ impl NgramSetIter {
             inner,
             reached_end: false,
             is_initial: true,
+            remaining: None,
         }
     }
+
+    /// Set the number of items this iterator is known to yield, which is reported by `size_hint`.
+    pub(crate) fn with_len(mut self, len: usize) -> Self {
+        self.remaining = Some(len);
+        self
+    }
 }
 
 impl Iterator for NgramSetIter {
@@ -59,9 +67,22 @@ impl Iterator for NgramSetIter {
             return None;
         }
 
+        if let Some(remaining) = self.remaining.as_mut() {
+            *remaining = remaining.saturating_sub(1);
+        }
         let ngram = NgramSetIterItem { inner: self.inner };
         Some(ngram)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.reached_end {
+            return (0, Some(0));
+        }
+        match self.remaining {
+            Some(remaining) => (remaining, Some(remaining)),
+            None => (0, None),
+        }
+    }
 }
 
 impl Drop for NgramSetIter {
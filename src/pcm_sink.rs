@@ -0,0 +1,113 @@
+use std::io::Write;
+
+use crate::decoder::Decoder;
+
+/// Number of samples passed to the decoder at once.
+const SINK_CHUNK_SAMPLES: usize = 4096;
+
+/// Writer that feeds raw PCM bytes to a decoder, created with `Decoder::pcm_sink()`.
+///
+/// Written bytes are buffered until a full chunk of samples is available. Call `flush()` (or drop the sink) to process the remaining samples.
+/// A trailing odd byte is kept until the next write completes the sample.
+pub struct PcmSink<'a> {
+    decoder: &'a mut Decoder,
+    bytes: Vec<u8>,
+}
+
+impl<'a> PcmSink<'a> {
+    pub(crate) fn new(decoder: &'a mut Decoder) -> Self {
+        Self {
+            decoder,
+            bytes: Vec::with_capacity(SINK_CHUNK_SAMPLES * 2),
+        }
+    }
+
+    /// Process all complete samples in the buffer.
+    fn process_buffered(&mut self) -> std::io::Result<()> {
+        let n_bytes = self.bytes.len() - self.bytes.len() % 2;
+        if n_bytes == 0 {
+            return Ok(());
+        }
+        // Keep the byte order of the input, ps_process_raw() swaps according to -input_endian
+        let samples: Vec<i16> = self.bytes[..n_bytes]
+            .chunks_exact(2)
+            .map(|bytes| i16::from_ne_bytes([bytes[0], bytes[1]]))
+            .collect();
+        self.bytes.drain(..n_bytes);
+        self.decoder
+            .process_raw(&samples, false, false)
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        Ok(())
+    }
+}
+
+impl Write for PcmSink<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.bytes.extend_from_slice(buf);
+        if self.bytes.len() >= SINK_CHUNK_SAMPLES * 2 {
+            self.process_buffered()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.process_buffered()
+    }
+}
+
+impl Drop for PcmSink<'_> {
+    fn drop(&mut self) {
+        // Errors can not be reported here, call flush() to handle them
+        let _ = self.process_buffered();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use crate::test_util::{default_decoder, goforward_raw, swap_bytes, GOFORWARD_TEXT};
+
+    /// Write the bytes in odd-sized pieces, so samples are split across writes.
+    fn decode_with_sink(params: &[(&str, &str)], raw: &[u8]) -> String {
+        let mut decoder = default_decoder(params);
+        decoder.start_utt().unwrap();
+        {
+            let mut sink = decoder.pcm_sink();
+            for piece in raw.chunks(1001) {
+                sink.write_all(piece).unwrap();
+            }
+            sink.flush().unwrap();
+        }
+        decoder.end_utt().unwrap();
+        decoder.get_hyp().unwrap().unwrap().0
+    }
+
+    #[test]
+    fn sink_decodes_little_endian_bytes() {
+        let raw = goforward_raw();
+        assert_eq!(
+            decode_with_sink(&[("input_endian", "little")], &raw),
+            GOFORWARD_TEXT
+        );
+    }
+
+    #[test]
+    fn sink_decodes_big_endian_bytes() {
+        let raw = swap_bytes(&goforward_raw());
+        assert_eq!(
+            decode_with_sink(&[("input_endian", "big")], &raw),
+            GOFORWARD_TEXT
+        );
+    }
+
+    #[test]
+    fn sink_keeps_trailing_odd_byte() {
+        let mut decoder = default_decoder(&[]);
+        decoder.start_utt().unwrap();
+        let mut sink = decoder.pcm_sink();
+        sink.write_all(&[1, 2, 3]).unwrap();
+        sink.flush().unwrap();
+        assert_eq!(sink.bytes, [3]);
+    }
+}
@@ -1,4 +1,33 @@
-use crate::decoder::Decoder;
+use crate::{decoder::Decoder, fsg::FSG, ngram::Ngram};
+
+/// Kind of a decoder search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchKind {
+    /// N-Gram language model search.
+    Ngram,
+    /// Finite state grammar search (including JSGF grammars).
+    Fsg,
+    /// Keyphrase spotting search.
+    Kws,
+    /// Phoneme recognition search.
+    Allphone,
+    /// Forced alignment search.
+    Align,
+    /// A search whose kind the C API does not expose, e.g. a phone loop search not added through this wrapper.
+    Unknown,
+}
+
+/// Object backing the active search of a decoder, see `Decoder::active_search_object()`.
+pub enum ActiveSearch {
+    /// Language model (or set of language models) of an N-Gram search.
+    Ngram(Ngram),
+    /// Grammar of an FSG search.
+    Fsg(FSG),
+    /// Keyphrase of a keyphrase spotting search.
+    Kws(String),
+    /// A search without an associated object (phone loop or alignment), or `None` if no search is active.
+    Other(Option<SearchKind>),
+}
 
 pub struct SearchIter {
     inner: *mut pocketsphinx_sys::ps_search_iter_t,
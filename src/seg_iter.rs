@@ -1,36 +1,47 @@
+use std::marker::PhantomData;
+
 use crate::{decoder::Decoder, nbest_iter::NBest};
 
-pub struct SegIter {
+/// Iterator over the word segmentation of a hypothesis.
+///
+/// The iterator borrows the `Decoder` or `NBest` it was created from, so it can not outlive the hypothesis it points into.
+pub struct SegIter<'a> {
     inner: *mut pocketsphinx_sys::ps_seg_t,
     reached_end: bool,
     is_initial: bool,
+    index: usize,
+    _source: PhantomData<&'a ()>,
 }
 
-impl SegIter {
-    pub fn from_decoder(decoder: &Decoder) -> Option<Self> {
+impl<'a> SegIter<'a> {
+    pub fn from_decoder(decoder: &'a Decoder) -> Option<Self> {
         let inner = unsafe { pocketsphinx_sys::ps_seg_iter(decoder.get_inner()) };
         if inner.is_null() {
-            return None;
+            None
         } else {
             Some(Self {
                 inner,
                 reached_end: false,
                 is_initial: true,
+                index: 0,
+                _source: PhantomData,
             })
         }
     }
 
-    pub fn from_nbest(nbest: &NBest) -> Self {
+    pub fn from_nbest(nbest: &'a NBest<'_>) -> Self {
         let inner = unsafe { pocketsphinx_sys::ps_nbest_seg(nbest.get_inner()) };
         Self {
             inner,
             reached_end: false,
             is_initial: true,
+            index: 0,
+            _source: PhantomData,
         }
     }
 }
 
-impl Iterator for SegIter {
+impl Iterator for SegIter<'_> {
     type Item = Seg;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -49,12 +60,16 @@ impl Iterator for SegIter {
             return None;
         }
 
-        let seg = Seg { inner: self.inner };
+        let seg = Seg {
+            inner: self.inner,
+            index: self.index,
+        };
+        self.index += 1;
         Some(seg)
     }
 }
 
-impl Drop for SegIter {
+impl Drop for SegIter<'_> {
     fn drop(&mut self) {
         if !self.reached_end {
             unsafe { pocketsphinx_sys::ps_seg_free(self.inner) };
@@ -64,9 +79,18 @@ impl Drop for SegIter {
 
 pub struct Seg {
     inner: *mut pocketsphinx_sys::ps_seg_t,
+    index: usize,
 }
 
 impl Seg {
+    /// Get the position of this segment within the utterance.
+    ///
+    /// # Returns
+    /// Zero-based index of the segment in the order it was returned by the iterator.
+    pub fn get_index(&self) -> usize {
+        self.index
+    }
+
     /// Get word string from a segmentation iterator.
     ///
     /// # Returns
@@ -113,6 +137,43 @@ impl Seg {
     }
 }
 
+/// Timing of a single word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordSegment {
+    /// The word.
+    pub word: String,
+    /// First frame of the word.
+    pub start: i32,
+    /// Last frame of the word (inclusive).
+    pub end: i32,
+}
+
+impl WordSegment {
+    /// Get the start of the word in milliseconds.
+    ///
+    /// # Arguments
+    /// - `frame_rate` - Frames per second (`-frate`) of the decoder.
+    pub fn start_ms(&self, frame_rate: i32) -> u64 {
+        frames_to_ms(self.start, frame_rate)
+    }
+
+    /// Get the end of the word (the end of its last frame) in milliseconds, like the cue ends of `subtitle::word_cues()`.
+    ///
+    /// # Arguments
+    /// - `frame_rate` - Frames per second (`-frate`) of the decoder.
+    pub fn end_ms(&self, frame_rate: i32) -> u64 {
+        frames_to_ms(self.end.saturating_add(1), frame_rate)
+    }
+}
+
+/// Convert a frame index to milliseconds using integer arithmetic, negative frames and frame rates yield `0`.
+fn frames_to_ms(frame: i32, frame_rate: i32) -> u64 {
+    if frame_rate <= 0 {
+        return 0;
+    }
+    frame.max(0) as u64 * 1000 / frame_rate as u64
+}
+
 pub struct SegFrames {
     /// First frame index in segment.
     pub start: i32,
@@ -131,3 +192,194 @@ pub struct SegProp {
     /// This field is, of course, only meaningful for N-Gram models.
     pub lm_back: i32,
 }
+
+/// Remove the alternate pronunciation marker from a dictionary word, e.g. `the(2)` becomes `the`.
+pub(crate) fn strip_alternate_pronunciation(word: &str) -> &str {
+    match word.find('(') {
+        Some(pos) => &word[..pos],
+        None => word,
+    }
+}
+
+/// Check whether a segment word is a filler (silence, sentence markers or noise) rather than a recognized word.
+pub(crate) fn is_filler_word(word: &str) -> bool {
+    word.starts_with('<') || word.starts_with('[') || word.starts_with("++")
+}
+
+/// Sentence markers, silence and filler words of a model, see `Decoder::boundary_symbols()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoundarySymbols {
+    /// Sentence start marker.
+    pub start: String,
+    /// Sentence end marker.
+    pub end: String,
+    /// Silence word.
+    pub silence: String,
+    /// Words of the filler dictionary, e.g. noise words. Empty if the filler dictionary is not known.
+    pub fillers: Vec<String>,
+}
+
+impl BoundarySymbols {
+    /// Collect the symbols from the content of a filler dictionary (`noisedict`).
+    ///
+    /// The sentence markers are fixed by pocketsphinx, the silence word is the first other word pronounced as `SIL`.
+    pub(crate) fn from_filler_dict(content: &str) -> Self {
+        let mut symbols = Self::default();
+        let mut silence = None;
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with("##") || line.starts_with(";;") {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let word = strip_alternate_pronunciation(fields.next().unwrap_or_default());
+            let phones: Vec<&str> = fields.collect();
+            if silence.is_none()
+                && phones == ["SIL"]
+                && word != symbols.start
+                && word != symbols.end
+            {
+                silence = Some(word.to_string());
+            }
+            symbols.fillers.push(word.to_string());
+        }
+        if let Some(silence) = silence {
+            symbols.silence = silence;
+        }
+        symbols
+    }
+
+    /// Check whether a segment word is a filler (silence, sentence markers or noise) rather than a recognized word.
+    ///
+    /// If the filler dictionary is not known, common filler notations (`<...>`, `[...]`, `++...++`) are treated as fillers.
+    pub fn is_filler(&self, word: &str) -> bool {
+        let word = strip_alternate_pronunciation(word);
+        if self.fillers.is_empty() {
+            return is_filler_word(word);
+        }
+        word == self.start
+            || word == self.end
+            || word == self.silence
+            || self.fillers.iter().any(|filler| filler == word)
+    }
+}
+
+impl Default for BoundarySymbols {
+    fn default() -> Self {
+        Self {
+            start: "<s>".to_string(),
+            end: "</s>".to_string(),
+            silence: "<sil>".to_string(),
+            fillers: Vec::new(),
+        }
+    }
+}
+
+/// Coalesce consecutive segments of the same word into one segment spanning all of them.
+///
+/// Keyphrase spotting and phone recognition sometimes repeat a token across frame boundaries, this cleans up such output.
+///
+/// # Returns
+/// The merged segments in order.
+pub fn merge_repeats(segments: Vec<WordSegment>) -> Vec<WordSegment> {
+    let mut merged: Vec<WordSegment> = Vec::with_capacity(segments.len());
+    for segment in segments {
+        match merged.last_mut() {
+            Some(last) if last.word == segment.word => {
+                last.start = last.start.min(segment.start);
+                last.end = last.end.max(segment.end);
+            }
+            _ => merged.push(segment),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{decode_utterance, default_decoder, goforward_samples};
+
+    fn segments(words: &[(&str, i32, i32)]) -> Vec<WordSegment> {
+        words
+            .iter()
+            .map(|&(word, start, end)| WordSegment {
+                word: word.to_string(),
+                start,
+                end,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merge_adjacent_duplicates() {
+        let merged = merge_repeats(segments(&[
+            ("AH", 0, 4),
+            ("AH", 5, 9),
+            ("AH", 10, 12),
+            ("B", 13, 20),
+        ]));
+        assert_eq!(merged, segments(&[("AH", 0, 12), ("B", 13, 20)]));
+    }
+
+    #[test]
+    fn keep_non_adjacent_duplicates() {
+        let input = segments(&[("hello", 0, 10), ("world", 11, 20), ("hello", 21, 30)]);
+        assert_eq!(merge_repeats(input.clone()), input);
+    }
+
+    #[test]
+    fn fillers_separate_words() {
+        let input = segments(&[("hello", 0, 10), ("<sil>", 11, 15), ("hello", 16, 25)]);
+        assert_eq!(merge_repeats(input.clone()), input);
+    }
+
+    #[test]
+    fn merge_adjacent_fillers() {
+        let merged = merge_repeats(segments(&[
+            ("<sil>", 0, 5),
+            ("<sil>", 6, 9),
+            ("go", 10, 30),
+            ("<sil>", 31, 40),
+        ]));
+        assert_eq!(
+            merged,
+            segments(&[("<sil>", 0, 9), ("go", 10, 30), ("<sil>", 31, 40)])
+        );
+    }
+
+    #[test]
+    fn merge_empty() {
+        assert!(merge_repeats(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn word_segment_ms_covers_last_frame() {
+        let segment = WordSegment {
+            word: "forward".to_string(),
+            start: 10,
+            end: 49,
+        };
+        assert_eq!(segment.start_ms(100), 100);
+        assert_eq!(segment.end_ms(100), 500);
+        assert_eq!(segment.end_ms(0), 0);
+    }
+
+    #[test]
+    fn seg_index_counts_segments() {
+        let mut decoder = default_decoder(&[]);
+        decode_utterance(&mut decoder, &goforward_samples()).unwrap();
+
+        let indices: Vec<usize> = decoder
+            .get_seg_iter()
+            .unwrap()
+            .map(|seg| seg.get_index())
+            .collect();
+        assert!(indices.len() >= 4);
+        assert_eq!(indices, (0..indices.len()).collect::<Vec<_>>());
+
+        let mut nbest = decoder.get_nbest_iter().unwrap();
+        let best = nbest.next().unwrap();
+        let indices: Vec<usize> = best.get_seg().map(|seg| seg.get_index()).collect();
+        assert_eq!(indices, (0..indices.len()).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,79 @@
+use std::error::Error;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::decoder::Decoder;
+
+/// Decoder that can be shared between threads.
+///
+/// A `Decoder` can not be used concurrently, so every call locks the decoder for its duration.
+/// Cloning is cheap and yields a handle to the same decoder, e.g. for each request handler of a web server.
+///
+/// ```rust
+/// let shared = SharedDecoder::new(Decoder::try_from(Config::default()?)?);
+/// let handle = shared.clone();
+/// std::thread::spawn(move || handle.decode_samples(&samples));
+/// ```
+#[derive(Clone)]
+pub struct SharedDecoder(Arc<Mutex<Decoder>>);
+
+impl SharedDecoder {
+    /// Wrap a decoder for shared use.
+    ///
+    /// Other handles to the same pocketsphinx decoder (see `Decoder::retain()`) are not protected by the lock and must not be used while it is shared.
+    pub fn new(decoder: Decoder) -> Self {
+        Self(Arc::new(Mutex::new(decoder)))
+    }
+
+    /// Lock the decoder for exclusive access, e.g. to call several methods without other threads interfering.
+    pub fn lock(&self) -> Result<MutexGuard<'_, Decoder>, Box<dyn Error>> {
+        self.0
+            .lock()
+            .map_err(|_| "Shared decoder is poisoned by a panic in another thread".into())
+    }
+
+    /// Decode audio as a complete utterance.
+    ///
+    /// # Arguments
+    /// - `samples` - Audio with the sample rate of the decoder configuration.
+    ///
+    /// # Returns
+    /// The hypothesis string and path score, or `None` if nothing was recognized.
+    pub fn decode_samples(&self, samples: &[i16]) -> Result<Option<(String, i32)>, Box<dyn Error>> {
+        let mut decoder = self.lock()?;
+        decoder.start_utt()?;
+        decoder.process_raw(samples, false, true)?;
+        decoder.end_utt()?;
+        decoder.get_hyp()
+    }
+
+    /// Get the hypothesis of the last decoded utterance.
+    ///
+    /// Note that another thread may have decoded an utterance in the meantime, use `SharedDecoder::decode_samples()` to get the hypothesis of a specific utterance.
+    pub fn get_hyp(&self) -> Result<Option<(String, i32)>, Box<dyn Error>> {
+        self.lock()?.get_hyp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SharedDecoder;
+    use crate::test_util::{default_decoder, goforward_samples, GOFORWARD_TEXT};
+
+    #[test]
+    fn decode_from_two_threads() {
+        let samples = goforward_samples();
+        let shared = SharedDecoder::new(default_decoder(&[]));
+
+        let threads: Vec<_> = (0..2)
+            .map(|_| {
+                let shared = shared.clone();
+                let samples = samples.clone();
+                std::thread::spawn(move || shared.decode_samples(&samples).unwrap())
+            })
+            .collect();
+        for thread in threads {
+            let (hyp, _score) = thread.join().unwrap().unwrap();
+            assert_eq!(hyp, GOFORWARD_TEXT);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use crate::{decoder::Decoder, endpointer::Endpointer, vad::VADMode};
+
+/// Recognition result reported by a streaming decoder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecognitionEvent {
+    /// Hypothesis of the utterance in progress. Reported whenever it changes.
+    Partial(String),
+    /// Hypothesis of an utterance after the end of speech was detected.
+    Final(String),
+}
+
+/// Handle to feed audio to a streaming decoder, created with `Decoder::into_streaming()`.
+///
+/// The audio is endpointed and decoded on a separate thread.
+/// Dropping the handle ends the stream without decoding the remaining audio and waits for the thread to exit.
+pub struct StreamingHandle {
+    sender: Option<Sender<Vec<i16>>>,
+    thread: Option<JoinHandle<Result<(), String>>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl StreamingHandle {
+    pub(crate) fn spawn(decoder: Decoder) -> (Self, Receiver<RecognitionEvent>) {
+        let (sender, samples) = channel();
+        let (events, receiver) = channel();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stopped = stopped.clone();
+            std::thread::spawn(move || {
+                run_streaming(decoder, samples, events, &stopped).map_err(|e| e.to_string())
+            })
+        };
+        let handle = Self {
+            sender: Some(sender),
+            thread: Some(thread),
+            stopped,
+        };
+        (handle, receiver)
+    }
+
+    /// Feed audio to the decoder.
+    ///
+    /// # Arguments
+    /// - `samples` - Audio with the sample rate of the decoder configuration, in chunks of any size.
+    pub fn feed(&self, samples: &[i16]) -> Result<(), Box<dyn Error>> {
+        self.sender
+            .as_ref()
+            .ok_or("Streaming decoder has stopped")?
+            .send(samples.to_vec())
+            .map_err(|_| "Streaming decoder has stopped, call finish() for the cause".into())
+    }
+
+    /// End the stream and wait until all audio has been decoded.
+    ///
+    /// The last utterance is finalized, so its `Final` event is received before the event channel closes.
+    ///
+    /// # Returns
+    /// An error if decoding failed.
+    pub fn finish(mut self) -> Result<(), Box<dyn Error>> {
+        self.join()
+    }
+
+    fn join(&mut self) -> Result<(), Box<dyn Error>> {
+        self.sender = None;
+        match self.thread.take().map(|thread| thread.join()) {
+            Some(Ok(result)) => result.map_err(|e| e.into()),
+            Some(Err(_)) => Err("Streaming decoder thread panicked".into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for StreamingHandle {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        // Errors can only be reported by finish()
+        let _ = self.join();
+    }
+}
+
+fn run_streaming(
+    mut decoder: Decoder,
+    samples: Receiver<Vec<i16>>,
+    events: Sender<RecognitionEvent>,
+    stopped: &AtomicBool,
+) -> Result<(), Box<dyn Error>> {
+    let sample_rate = decoder.get_config().get_float("samprate")? as i32;
+    let endpointer = Endpointer::new(None, None, VADMode::Loose, Some(sample_rate), None)?;
+    let frame_size = endpointer.get_frame_size();
+    let mut frame_cache = Vec::with_capacity(frame_size * 2);
+    let mut partial = None;
+
+    for chunk in samples {
+        if stopped.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        frame_cache.extend(chunk);
+        let n_samples = frame_cache.len() - frame_cache.len() % frame_size;
+        for frame in frame_cache[..n_samples].chunks_exact(frame_size) {
+            if let Some(speech) = endpointer.process(frame) {
+                process_speech(&mut decoder, speech, &mut partial, &events)?;
+                if !endpointer.get_in_speech() {
+                    finish_utterance(&mut decoder, &mut partial, &events)?;
+                }
+            }
+        }
+        frame_cache.drain(..n_samples);
+    }
+
+    if let Some(speech) = endpointer.end_stream(&frame_cache) {
+        process_speech(&mut decoder, speech, &mut partial, &events)?;
+    }
+    if decoder.is_utt_active() {
+        finish_utterance(&mut decoder, &mut partial, &events)?;
+    }
+    Ok(())
+}
+
+fn process_speech(
+    decoder: &mut Decoder,
+    speech: &[i16],
+    partial: &mut Option<String>,
+    events: &Sender<RecognitionEvent>,
+) -> Result<(), Box<dyn Error>> {
+    if !decoder.is_utt_active() {
+        decoder.start_utt()?;
+    }
+    decoder.process_raw(speech, false, false)?;
+    if let Some((hyp, _score)) = decoder.get_hyp()? {
+        if !hyp.is_empty() && partial.as_ref() != Some(&hyp) {
+            // Events are dropped if the receiver is gone, audio is still consumed so feed() keeps working
+            let _ = events.send(RecognitionEvent::Partial(hyp.clone()));
+            *partial = Some(hyp);
+        }
+    }
+    Ok(())
+}
+
+fn finish_utterance(
+    decoder: &mut Decoder,
+    partial: &mut Option<String>,
+    events: &Sender<RecognitionEvent>,
+) -> Result<(), Box<dyn Error>> {
+    decoder.end_utt()?;
+    *partial = None;
+    if let Some((hyp, _score)) = decoder.get_hyp()? {
+        if !hyp.is_empty() {
+            let _ = events.send(RecognitionEvent::Final(hyp));
+        }
+    }
+    Ok(())
+}
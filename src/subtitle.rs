@@ -0,0 +1,184 @@
+use std::io::Write;
+
+use crate::{decoder::Decoder, seg_iter::strip_alternate_pronunciation};
+
+/// A single subtitle cue.
+#[derive(Debug, Clone)]
+pub struct SubtitleCue {
+    /// Start time of the cue in seconds.
+    pub start: f64,
+    /// End time of the cue in seconds.
+    pub end: f64,
+    /// Text shown during the cue.
+    pub text: String,
+}
+
+/// Collect one cue per recognized word of the current hypothesis.
+///
+/// Fillers such as silence and noise are skipped and alternate pronunciation markers (e.g. `the(2)`) are removed.
+///
+/// # Returns
+/// The cues in order of their appearance. Empty if no hypothesis is available.
+pub fn word_cues(decoder: &Decoder) -> Result<Vec<SubtitleCue>, Box<dyn std::error::Error>> {
+    let frate = decoder.get_config().get_int("frate")? as f64;
+    let symbols = decoder.boundary_symbols();
+    let mut cues = Vec::new();
+    if let Some(seg_iter) = decoder.get_seg_iter() {
+        for seg in seg_iter {
+            let word = seg.get_word();
+            if symbols.is_filler(&word) {
+                continue;
+            }
+            let text = strip_alternate_pronunciation(&word).to_string();
+            let frames = seg.get_frames();
+            cues.push(SubtitleCue {
+                start: frames.start as f64 / frate,
+                // The end frame is inclusive
+                end: (frames.end + 1) as f64 / frate,
+                text,
+            });
+        }
+    }
+    Ok(cues)
+}
+
+/// Format a time as an SRT timestamp (`HH:MM:SS,mmm`).
+pub fn format_srt_timestamp(seconds: f64) -> String {
+    let total_ms = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        total_ms / 3_600_000,
+        (total_ms / 60_000) % 60,
+        (total_ms / 1000) % 60,
+        total_ms % 1000
+    )
+}
+
+/// Format a time as a WebVTT timestamp (`HH:MM:SS.mmm`).
+pub fn format_vtt_timestamp(seconds: f64) -> String {
+    format_srt_timestamp(seconds).replacen(',', ".", 1)
+}
+
+/// Write cues in the SubRip (SRT) format.
+///
+/// # Arguments
+/// - `w` - Writer receiving the subtitles.
+/// - `cues` - Cues to write. They are numbered starting at 1.
+pub fn write_srt<W: Write>(w: &mut W, cues: &[SubtitleCue]) -> std::io::Result<()> {
+    for (i, cue) in cues.iter().enumerate() {
+        writeln!(w, "{}", i + 1)?;
+        writeln!(
+            w,
+            "{} --> {}",
+            format_srt_timestamp(cue.start),
+            format_srt_timestamp(cue.end)
+        )?;
+        writeln!(w, "{}", cue.text)?;
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+/// Write cues in the WebVTT format, e.g. for `<track>` captions in browsers.
+///
+/// # Arguments
+/// - `w` - Writer receiving the subtitles.
+/// - `cues` - Cues to write after the `WEBVTT` header.
+pub fn write_vtt<W: Write>(w: &mut W, cues: &[SubtitleCue]) -> std::io::Result<()> {
+    writeln!(w, "WEBVTT")?;
+    writeln!(w)?;
+    for cue in cues {
+        writeln!(
+            w,
+            "{} --> {}",
+            format_vtt_timestamp(cue.start),
+            format_vtt_timestamp(cue.end)
+        )?;
+        writeln!(w, "{}", cue.text)?;
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cues() -> Vec<SubtitleCue> {
+        vec![
+            SubtitleCue {
+                start: 0.0,
+                end: 0.5,
+                text: "go".to_string(),
+            },
+            SubtitleCue {
+                start: 3661.25,
+                end: 3662.0,
+                text: "forward".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn srt_timestamps() {
+        let cases = [
+            (0.0, "00:00:00,000"),
+            (0.001, "00:00:00,001"),
+            (0.0004, "00:00:00,000"),
+            (0.0006, "00:00:00,001"),
+            (59.999, "00:00:59,999"),
+            (60.0, "00:01:00,000"),
+            (3599.9994, "00:59:59,999"),
+            (3599.9996, "01:00:00,000"),
+            (3661.25, "01:01:01,250"),
+            (360000.0, "100:00:00,000"),
+            (-1.0, "00:00:00,000"),
+        ];
+        for (seconds, expected) in cases {
+            assert_eq!(format_srt_timestamp(seconds), expected, "{} s", seconds);
+        }
+    }
+
+    #[test]
+    fn srt_output() {
+        let mut output = Vec::new();
+        write_srt(&mut output, &cues()).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "1\n00:00:00,000 --> 00:00:00,500\ngo\n\n2\n01:01:01,250 --> 01:01:02,000\nforward\n\n"
+        );
+    }
+
+    #[test]
+    fn vtt_timestamps() {
+        let cases = [
+            (0.0, "00:00:00.000"),
+            (0.0006, "00:00:00.001"),
+            (59.999, "00:00:59.999"),
+            (3599.9994, "00:59:59.999"),
+            (3599.9996, "01:00:00.000"),
+            (3661.25, "01:01:01.250"),
+            (-1.0, "00:00:00.000"),
+        ];
+        for (seconds, expected) in cases {
+            assert_eq!(format_vtt_timestamp(seconds), expected, "{} s", seconds);
+        }
+    }
+
+    #[test]
+    fn vtt_output() {
+        let mut output = Vec::new();
+        write_vtt(&mut output, &cues()).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "WEBVTT\n\n00:00:00.000 --> 00:00:00.500\ngo\n\n01:01:01.250 --> 01:01:02.000\nforward\n\n"
+        );
+    }
+
+    #[test]
+    fn vtt_output_without_cues() {
+        let mut output = Vec::new();
+        write_vtt(&mut output, &[]).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "WEBVTT\n\n");
+    }
+}
@@ -0,0 +1,56 @@
+//! Helpers shared by the unit tests.
+
+use crate::{Config, Decoder};
+
+/// Transcript of `goforward.raw`.
+pub const GOFORWARD_TEXT: &str = "go forward ten meters";
+
+/// Path of `goforward.raw`, a raw 16-bit little-endian 16 kHz recording shipped with the pocketsphinx sources.
+pub const GOFORWARD_RAW_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/sys/pocketsphinx/test/data/goforward.raw"
+);
+
+/// Content of `goforward.raw`.
+pub fn goforward_raw() -> Vec<u8> {
+    std::fs::read(GOFORWARD_RAW_PATH).expect("pocketsphinx submodule is not checked out")
+}
+
+/// Samples of `goforward.raw`.
+pub fn goforward_samples() -> Vec<i16> {
+    goforward_raw()
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect()
+}
+
+/// Swap the byte order of every 16-bit sample.
+pub fn swap_bytes(bytes: &[u8]) -> Vec<u8> {
+    bytes.chunks_exact(2).flat_map(|b| [b[1], b[0]]).collect()
+}
+
+/// Create a decoder with the default model and the given string parameters.
+pub fn default_decoder(params: &[(&str, &str)]) -> Decoder {
+    let mut config = Config::default().unwrap();
+    for (name, value) in params {
+        config.set_str(name, value).unwrap();
+    }
+    config.init_decoder().unwrap()
+}
+
+/// Decode samples as a complete utterance.
+pub fn decode_utterance(decoder: &mut Decoder, samples: &[i16]) -> Option<(String, i32)> {
+    decoder.start_utt().unwrap();
+    decoder.process_raw(samples, false, true).unwrap();
+    decoder.end_utt().unwrap();
+    decoder.get_hyp().unwrap()
+}
+
+/// Path of a file in the temporary directory that is unique to this process and `name`.
+pub fn temp_path(name: &str) -> String {
+    std::env::temp_dir()
+        .join(format!("pocketsphinx-rs-{}-{}", std::process::id(), name))
+        .to_str()
+        .unwrap()
+        .to_string()
+}
@@ -0,0 +1,20 @@
+/// Splits text into the words used for scoring and alignment.
+///
+/// Languages differ in how words are delimited, e.g. CJK text is not separated by whitespace.
+/// Implement this trait to supply the tokenization matching the dictionary and language model.
+pub trait Tokenizer {
+    /// Split `text` into words.
+    fn tokenize(&self, text: &str) -> Vec<String>;
+}
+
+/// Tokenizer splitting text on whitespace, used by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WhitespaceTokenizer;
+
+impl Tokenizer for WhitespaceTokenizer {
+    fn tokenize(&self, text: &str) -> Vec<String> {
+        text.split_whitespace()
+            .map(|word| word.to_string())
+            .collect()
+    }
+}
@@ -0,0 +1,93 @@
+use std::error::Error;
+
+use crate::{
+    audio::read_wav_i16, config::Config, decoder::Decoder, endpointer::Endpointer, vad::VADMode,
+};
+
+/// A single transcribed utterance.
+#[derive(Debug, Clone)]
+pub struct Utterance {
+    /// Recognized text of the utterance.
+    pub text: String,
+    /// Start time of the utterance in seconds.
+    pub start: f64,
+    /// End time of the utterance in seconds.
+    pub end: f64,
+}
+
+/// Transcribe a whole WAV file.
+///
+/// The audio is split into utterances with an `Endpointer` and every utterance is decoded separately.
+///
+/// ```rust
+/// let config = Config::default()?;
+/// for utterance in transcribe_file(&config, "audio.wav")? {
+///     println!("[{:.2} - {:.2}] {}", utterance.start, utterance.end, utterance.text);
+/// }
+/// ```
+///
+/// # Arguments
+/// - `config` - Configuration used to initialize the decoder. Its `samprate` must match the sample rate of the file.
+/// - `path`   - Path to a 16-bit mono PCM WAV file.
+///
+/// # Returns
+/// The recognized utterances in order of their appearance. Utterances without a hypothesis are skipped.
+pub fn transcribe_file(config: &Config, path: &str) -> Result<Vec<Utterance>, Box<dyn Error>> {
+    let (samples, sample_rate) = read_wav_i16(path)?;
+    let config_sample_rate = config.get_float("samprate")?;
+    if sample_rate as f64 != config_sample_rate {
+        return Err(format!(
+            "Sample rate of {} ({} Hz) does not match the configured samprate ({} Hz)",
+            path, sample_rate, config_sample_rate
+        )
+        .into());
+    }
+
+    let mut decoder = Decoder::new(None)?;
+    decoder.reinit(config)?;
+    let endpointer = Endpointer::new(None, None, VADMode::Loose, Some(sample_rate as i32), None)?;
+
+    let utterances = endpointer
+        .decode_stream(&mut decoder, &samples)?
+        .into_iter()
+        .map(|(start, end, text)| Utterance { text, start, end })
+        .collect();
+    Ok(utterances)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::write_wav;
+    use crate::test_util::{goforward_samples, temp_path};
+
+    #[test]
+    fn transcribe_file_finds_speech() {
+        let path = temp_path("transcribe.wav");
+        let mut samples = vec![0; 16000];
+        samples.extend(goforward_samples());
+        samples.extend(vec![0; 16000]);
+        write_wav(&path, &samples, 16000).unwrap();
+
+        let config = Config::default().unwrap();
+        let utterances = transcribe_file(&config, &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(!utterances.is_empty());
+        let text: Vec<&str> = utterances.iter().map(|u| u.text.as_str()).collect();
+        assert!(text.join(" ").contains("forward"));
+        for utterance in &utterances {
+            assert!(utterance.start >= 0.5 && utterance.start < utterance.end);
+            assert!(utterance.end <= samples.len() as f64 / 16000.0);
+        }
+    }
+
+    #[test]
+    fn transcribe_file_rejects_sample_rate_mismatch() {
+        let path = temp_path("transcribe-8k.wav");
+        write_wav(&path, &[0; 8000], 8000).unwrap();
+        let result = transcribe_file(&Config::default().unwrap(), &path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+}
@@ -13,15 +13,15 @@ impl VAD {
     /// # Arguments
     /// - `mode`            - "Aggressiveness" of voice activity detection. Stricter values are less likely to misclassify non-speech as speech.
     /// - `sample_rate`     - Sampling rate of input, or `None` for default (which can be obtained with `VAD::get_sample_rate()`). Only `8000`, `16000`, `32000`, `48000` are directly supported.
-    ///                       See `VAD::set_input_params()` for more information.
-    /// - `frame_length`    - Frame length in seconds, or `None` for the default. Only `0.01`, `0.02`, `0.03` currently supported. Actual value may differ, you must use `VAD::get_frame_length()` to obtain it.
+    ///   See `VAD::set_input_params()` for more information.
+    /// - `frame_length`    - Frame length in seconds, or `None` for the default. Only `0.01`, `0.02`, `0.03` currently supported. Actual value may differ, you must use `VAD::get_frame_length_seconds()` to obtain it.
     pub fn new(
         mode: VADMode,
         sample_rate: Option<i32>,
         frame_length: Option<f64>,
     ) -> Result<Self, Box<dyn Error>> {
-        let sample_rate = sample_rate.unwrap_or_else(|| 0);
-        let frame_length = frame_length.unwrap_or_else(|| 0.0);
+        let sample_rate = sample_rate.unwrap_or(0);
+        let frame_length = frame_length.unwrap_or(0.0);
         let inner =
             unsafe { pocketsphinx_sys::ps_vad_init(mode as u32, sample_rate, frame_length) };
         if inner.is_null() {
@@ -47,9 +47,8 @@ impl VAD {
     ///
     /// # Returns
     /// Voice activity detector with incremented reference count.
-    pub fn retain(&mut self) -> Self {
+    pub fn retain(&self) -> Self {
         let retained_inner = unsafe { pocketsphinx_sys::ps_vad_retain(self.inner) };
-        self.retained = true;
         Self {
             inner: retained_inner,
             retained: false,
@@ -60,16 +59,16 @@ impl VAD {
     ///
     /// # Arguments
     /// - `sample_rate`     - Sampling rate of input, or `None` for default (which can be obtained with `VAD::get_sample_rate()`). Only `8000`, `16000`, `32000`, `48000` are directly supported, others will use the closest supported rate (within reason).
-    ///                       Note that this means that the actual frame length may not be exactly the one requested, so you must always use the one returned by `VAD::get_frame_size()` (in samples) or `VAD::get_frame_length()` (in seconds).
+    ///   Note that this means that the actual frame length may not be exactly the one requested, so you must always use the one returned by `VAD::get_frame_size()` (in samples) or `VAD::get_frame_length_seconds()` (in seconds).
     /// - `frame_length`    - Requested frame length in seconds, or `None` for the default. Only `0.01`, `0.02`, `0.03` currently supported.
-    ///                       Actual frame length may be different, you must always use `VAD::get_frame_length()` to obtain it.
+    ///   Actual frame length may be different, you must always use `VAD::get_frame_length_seconds()` to obtain it.
     pub fn set_input_params(
         &mut self,
         sample_rate: Option<i32>,
         frame_length: Option<f64>,
     ) -> Result<(), Box<dyn Error>> {
-        let sample_rate = sample_rate.unwrap_or_else(|| 0);
-        let frame_length = frame_length.unwrap_or_else(|| 0.0);
+        let sample_rate = sample_rate.unwrap_or(0);
+        let frame_length = frame_length.unwrap_or(0.0);
         let result = unsafe {
             pocketsphinx_sys::ps_vad_set_input_params(self.inner, sample_rate, frame_length)
         };
@@ -85,8 +84,20 @@ impl VAD {
     /// # Returns
     /// Expected sampling rate.
     pub fn get_sample_rate(&self) -> i32 {
-        let sample_rate = unsafe { pocketsphinx_sys::ps_vad_sample_rate(self.inner) };
-        sample_rate
+        unsafe { pocketsphinx_sys::ps_vad_sample_rate(self.inner) }
+    }
+
+    /// Check whether the sample rate used by voice activity detection differs from the requested one.
+    ///
+    /// Unsupported sample rates are silently replaced with the closest supported rate, which also changes the frame size.
+    ///
+    /// # Arguments
+    /// - `requested` - Sample rate passed to `VAD::new()` or `VAD::set_input_params()`.
+    ///
+    /// # Returns
+    /// `true` if `VAD::get_sample_rate()` is not the requested rate.
+    pub fn rate_was_adjusted(&self, requested: i32) -> bool {
+        self.get_sample_rate() != requested
     }
 
     /// Get the number of samples expected by voice activity detection.
@@ -96,8 +107,7 @@ impl VAD {
     /// # Returns
     /// Size, in samples, of the frames passed to `VAD::classify()`.
     pub fn get_frame_size(&self) -> usize {
-        let frame_size = unsafe { pocketsphinx_sys::ps_vad_frame_size(self.inner) };
-        frame_size
+        unsafe { pocketsphinx_sys::ps_vad_frame_size(self.inner) }
     }
 
     /// Classify a frame as speech or not speech.
@@ -111,6 +121,44 @@ impl VAD {
         }
     }
 
+    /// Classify every frame of a buffer as speech or not speech.
+    ///
+    /// The audio is split into frames of `VAD::get_frame_size()` samples, the final partial frame is zero-padded.
+    ///
+    /// # Returns
+    /// One classification per frame in order of the audio.
+    pub fn classify_all(&mut self, audio: &[i16]) -> Vec<VADClass> {
+        let frame_size = self.get_frame_size();
+        let mut classes = Vec::with_capacity(audio.len().div_ceil(frame_size));
+        for frame in audio.chunks(frame_size) {
+            if frame.len() == frame_size {
+                classes.push(self.classify(frame));
+            } else {
+                let mut padded = frame.to_vec();
+                padded.resize(frame_size, 0);
+                classes.push(self.classify(&padded));
+            }
+        }
+        classes
+    }
+
+    /// Keep only the speech of a buffer, e.g. as a cheap noise gate before further processing.
+    ///
+    /// The audio is classified like in `VAD::classify_all()` and the frames classified as speech are concatenated.
+    ///
+    /// # Returns
+    /// The speech samples in order of the audio. Empty if no speech was detected.
+    pub fn speech_gate(&mut self, audio: &[i16]) -> Vec<i16> {
+        let frame_size = self.get_frame_size();
+        let classes = self.classify_all(audio);
+        audio
+            .chunks(frame_size)
+            .zip(classes)
+            .filter(|(_, class)| *class == VADClass::Speech)
+            .flat_map(|(frame, _)| frame.iter().copied())
+            .collect()
+    }
+
     /// Default sampling rate for voice activity detector.
     ///
     /// @see https://cmusphinx.github.io/doc/pocketsphinx/vad_8h.html#a619d5a74e526164718dfee5ed9a48202
@@ -125,13 +173,23 @@ impl VAD {
         0.03
     }
 
+    /// Get the _actual_ length of a frame in milliseconds (rounded down).
+    ///
+    /// This may differ from the value requested in `VAD::set_input_params()`. Use `VAD::get_frame_length_seconds()` for the exact length.
+    ///
+    /// **Unit change:** earlier versions documented this as seconds, but the integer division always returned `0`.
+    /// It now returns milliseconds, e.g. `30` for the default frame length, so callers expecting seconds must switch to `VAD::get_frame_length_seconds()`.
+    pub fn get_frame_length(&self) -> i32 {
+        (self.get_frame_size() as i64 * 1000 / self.get_sample_rate() as i64) as i32
+    }
+
     /// Get the _actual_ length of a frame in seconds.
     ///
     /// This may differ from the value requested in `VAD::set_input_params()`.
     ///
     /// @see https://cmusphinx.github.io/doc/pocketsphinx/vad_8h.html#a8605289aba98c9ef20a53d77fd1bcfe4
-    pub fn get_frame_length(&self) -> i32 {
-        self.get_frame_size() as i32 / self.get_sample_rate()
+    pub fn get_frame_length_seconds(&self) -> f64 {
+        self.get_frame_size() as f64 / self.get_sample_rate() as f64
     }
 }
 
@@ -150,8 +208,57 @@ pub enum VADMode {
     Strict = 3,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum VADClass {
     Error = -1,
     NotSpeech = 0,
     Speech = 1,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::goforward_samples;
+
+    #[test]
+    fn rate_was_adjusted_for_unsupported_rate() {
+        let vad = VAD::new(VADMode::Loose, Some(44100), None).unwrap();
+        assert!(vad.rate_was_adjusted(44100));
+        assert_ne!(vad.get_sample_rate(), 44100);
+        assert!(!vad.rate_was_adjusted(vad.get_sample_rate()));
+
+        let vad = VAD::new(VADMode::Loose, Some(16000), None).unwrap();
+        assert!(!vad.rate_was_adjusted(16000));
+    }
+
+    #[test]
+    fn classify_all_finds_speech() {
+        let mut vad = VAD::new(VADMode::Loose, Some(16000), None).unwrap();
+        let frame_size = vad.get_frame_size();
+        let samples = goforward_samples();
+        let classes = vad.classify_all(&samples);
+        assert_eq!(classes.len(), samples.len().div_ceil(frame_size));
+        assert!(classes.contains(&VADClass::Speech));
+        assert!(!classes.contains(&VADClass::Error));
+
+        let classes = vad.classify_all(&vec![0; frame_size * 2 + 1]);
+        assert_eq!(classes, vec![VADClass::NotSpeech; 3]);
+    }
+
+    #[test]
+    fn speech_gate_drops_silence() {
+        let mut vad = VAD::new(VADMode::Loose, Some(16000), None).unwrap();
+        let frame_size = vad.get_frame_size();
+        let mut audio = goforward_samples();
+        audio.truncate(audio.len() / frame_size * frame_size);
+        let n_speech_samples = audio.len();
+        audio.extend(vec![0; frame_size * 50]);
+
+        let speech = vad.speech_gate(&audio);
+        assert!(!speech.is_empty());
+        assert!(speech.len() <= n_speech_samples);
+        assert_eq!(speech.len() % frame_size, 0);
+
+        assert!(vad.speech_gate(&vec![0; frame_size * 10]).is_empty());
+    }
+}
@@ -0,0 +1,178 @@
+use std::error::Error;
+
+use crate::{audio::RingBuffer, config::Config, decoder::Decoder};
+
+/// Name of the keyphrase search used by `Wakeword`.
+const WAKEWORD_SEARCH: &str = "_wakeword";
+
+/// Default seconds of audio kept before a detection.
+const DEFAULT_PRE_ROLL: f32 = 1.0;
+
+/// Default seconds of audio captured after a detection.
+const DEFAULT_COMMAND_DURATION: f32 = 3.0;
+
+/// A detected wakeword together with the surrounding audio.
+#[derive(Debug, Clone)]
+pub struct DetectionEvent {
+    /// Time of the detection in seconds since the first sample pushed to the detector.
+    pub timestamp: f64,
+    /// Audio preceding the detection, which contains the wakeword itself.
+    pub pre_roll: Vec<i16>,
+    /// Audio following the detection (e.g. the command spoken after the wakeword).
+    pub command_audio: Vec<i16>,
+}
+
+struct PendingDetection {
+    timestamp: f64,
+    pre_roll: Vec<i16>,
+    command_audio: Vec<i16>,
+    remaining: usize,
+}
+
+/// Streaming keyphrase detector.
+///
+/// Audio is pushed in chunks of any size. When the keyphrase is spotted, the detector captures the audio following it and reports a `DetectionEvent` once the command audio is complete.
+/// A single chunk may complete several detections.
+///
+/// ```rust
+/// let mut wakeword = Wakeword::new(Config::default()?, "oh mighty computer", 1e-20)?;
+/// for chunk in audio_chunks {
+///     for event in wakeword.push(&chunk)? {
+///         println!("Wakeword detected at {:.2}s", event.timestamp);
+///     }
+/// }
+/// ```
+pub struct Wakeword {
+    decoder: Decoder,
+    ring_buffer: RingBuffer,
+    sample_rate: f64,
+    frame_samples: usize,
+    command_samples: usize,
+    processed_samples: usize,
+    pending: Option<PendingDetection>,
+}
+
+impl Wakeword {
+    /// Create a keyphrase detector.
+    ///
+    /// # Arguments
+    /// - `config`    - Configuration used to initialize the decoder. The detector takes ownership of it.
+    /// - `phrase`    - Keyphrase to spot, all words must be in the dictionary.
+    /// - `threshold` - Detection threshold (`kws_threshold`), smaller values detect more but produce more false alarms.
+    pub fn new(config: Config, phrase: &str, threshold: f64) -> Result<Self, Box<dyn Error>> {
+        let mut decoder = Decoder::try_from(config)?;
+        let mut config = decoder.get_config();
+        let sample_rate = config.get_float("samprate")?;
+        let frame_rate = config.get_int("frate")? as f64;
+        // The threshold is read when the search is created
+        config.set_float("kws_threshold", threshold)?;
+        decoder.add_keyphrase(WAKEWORD_SEARCH, phrase)?;
+        decoder.set_activate_search(WAKEWORD_SEARCH)?;
+        decoder.start_utt()?;
+
+        Ok(Self {
+            decoder,
+            ring_buffer: RingBuffer::with_duration(sample_rate as u32, DEFAULT_PRE_ROLL),
+            sample_rate,
+            frame_samples: ((sample_rate / frame_rate) as usize).max(1),
+            command_samples: (sample_rate * DEFAULT_COMMAND_DURATION as f64) as usize,
+            processed_samples: 0,
+            pending: None,
+        })
+    }
+
+    /// Set how many seconds of audio before a detection are included in the event.
+    pub fn set_pre_roll(&mut self, seconds: f32) {
+        self.ring_buffer = RingBuffer::with_duration(self.sample_rate as u32, seconds);
+    }
+
+    /// Set how many seconds of audio after a detection are captured as command audio.
+    pub fn set_command_duration(&mut self, seconds: f32) {
+        self.command_samples = (self.sample_rate * seconds as f64) as usize;
+    }
+
+    /// Feed audio to the detector.
+    ///
+    /// While command audio is captured after a detection, the keyphrase is not spotted.
+    ///
+    /// # Arguments
+    /// - `samples` - Audio with the sample rate of the configuration.
+    ///
+    /// # Returns
+    /// A `DetectionEvent` for every detection whose command audio has been captured completely with these samples, in the order of detection.
+    pub fn push(&mut self, samples: &[i16]) -> Result<Vec<DetectionEvent>, Box<dyn Error>> {
+        let mut events = Vec::new();
+        let mut offset = 0;
+        while offset < samples.len() {
+            if let Some(pending) = self.pending.as_mut() {
+                let take = pending.remaining.min(samples.len() - offset);
+                pending
+                    .command_audio
+                    .extend_from_slice(&samples[offset..offset + take]);
+                pending.remaining -= take;
+                offset += take;
+                self.processed_samples += take;
+
+                if pending.remaining == 0 {
+                    let pending = self.pending.take().unwrap();
+                    events.push(DetectionEvent {
+                        timestamp: pending.timestamp,
+                        pre_roll: pending.pre_roll,
+                        command_audio: pending.command_audio,
+                    });
+                }
+                continue;
+            }
+
+            let end = (offset + self.frame_samples).min(samples.len());
+            let chunk = &samples[offset..end];
+            self.ring_buffer.push(chunk);
+            self.decoder.process_raw(chunk, false, false)?;
+            offset = end;
+            self.processed_samples += chunk.len();
+
+            if self.decoder.get_hyp()?.is_some() {
+                // Restart the utterance in order to reset the spotter
+                self.decoder.end_utt()?;
+                self.decoder.start_utt()?;
+                self.pending = Some(PendingDetection {
+                    timestamp: self.processed_samples as f64 / self.sample_rate,
+                    pre_roll: self.ring_buffer.latest(self.ring_buffer.capacity()),
+                    command_audio: Vec::with_capacity(self.command_samples),
+                    remaining: self.command_samples,
+                });
+                self.ring_buffer.clear();
+            }
+        }
+        Ok(events)
+    }
+
+    /// Get the decoder used for spotting.
+    pub fn get_decoder(&self) -> &Decoder {
+        &self.decoder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Wakeword;
+    use crate::test_util::goforward_samples;
+    use crate::Config;
+
+    #[test]
+    fn push_reports_every_detection() {
+        let utterance = goforward_samples();
+        let samples = [utterance.as_slice(), utterance.as_slice()].concat();
+
+        let mut wakeword = Wakeword::new(Config::default().unwrap(), "forward", 1e-20).unwrap();
+        wakeword.set_pre_roll(0.5);
+        wakeword.set_command_duration(0.5);
+        let events = wakeword.push(&samples).unwrap();
+
+        assert_eq!(events.len(), 2);
+        assert!(events[0].timestamp < events[1].timestamp);
+        for event in &events {
+            assert_eq!(event.command_audio.len(), 8000);
+        }
+    }
+}